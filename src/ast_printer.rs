@@ -0,0 +1,321 @@
+use crate::literal::Literal;
+use crate::parser::{Expr, Stmt};
+use crate::token::Token;
+
+/// Renders an `Expr` or `Stmt` in a parenthesized, Lisp-like prefix
+/// notation.
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn print(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => self.parenthesize(&operator.lexeme, &[left, right]),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.parenthesize(&operator.lexeme, &[left, right]),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut exprs = vec![callee.as_ref()];
+                exprs.extend(arguments);
+                self.parenthesize("call", &exprs)
+            }
+            Expr::Get { object, name } => {
+                self.parenthesize(&format!("get {}", name.lexeme), &[object])
+            }
+            Expr::Set {
+                object,
+                name,
+                operator,
+                value,
+            } => self.parenthesize(
+                &format!(
+                    "set{} {}",
+                    compound(operator).unwrap_or_default(),
+                    name.lexeme
+                ),
+                &[object, value],
+            ),
+            Expr::Array { elements, .. } => {
+                let elements: Vec<&Expr> = elements.iter().collect();
+                self.parenthesize("array", &elements)
+            }
+            Expr::Index { object, index, .. } => self.parenthesize("index", &[object, index]),
+            Expr::IndexSet {
+                object,
+                index,
+                operator,
+                value,
+                ..
+            } => self.parenthesize(
+                &format!("index{}", compound(operator).as_deref().unwrap_or("=")),
+                &[object, index, value],
+            ),
+            Expr::Map { entries, .. } => {
+                let exprs: Vec<&Expr> = entries
+                    .iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect();
+                self.parenthesize("map", &exprs)
+            }
+            Expr::This { .. } => "this".to_string(),
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => self.parenthesize("?:", &[condition, then_expr, else_expr]),
+            Expr::Function { params, .. } => format!("(fun ({}))", join_names(params)),
+            Expr::Grouping { expression, .. } => self.parenthesize("group", &[expression]),
+            Expr::Literal { value, .. } => self.literal(value),
+            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, &[right]),
+            Expr::Variable { name, .. } => name.lexeme.to_string(),
+            Expr::Assign { name, value, .. } => {
+                self.parenthesize(&format!("= {}", name.lexeme), &[value])
+            }
+        }
+    }
+
+    pub fn print_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression(expr) => self.parenthesize(";", &[expr]),
+            Stmt::Print(expr) => self.parenthesize("print", &[expr]),
+            Stmt::Write(expr) => self.parenthesize("write", &[expr]),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => self.parenthesize("return", &[value]),
+                None => "(return)".to_string(),
+            },
+            Stmt::Block(statements) => self.parenthesize_stmts("block", statements),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let mut head = format!("class {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    head.push_str(&format!(" < {}", self.print(superclass)));
+                }
+                self.parenthesize_stmts(&head, methods)
+            }
+            Stmt::Function { name, params, body } => {
+                let head = format!("fun {} ({})", name.lexeme, join_names(params));
+                self.parenthesize_stmts(&head, body)
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut output = format!(
+                    "(if {} {}",
+                    self.print(condition),
+                    self.print_stmt(then_branch)
+                );
+                if let Some(else_branch) = else_branch {
+                    output.push(' ');
+                    output.push_str(&self.print_stmt(else_branch));
+                }
+                output.push(')');
+
+                output
+            }
+            Stmt::Var { name, initializer } => {
+                let head = format!("var {}", name.lexeme);
+                match initializer {
+                    Some(initializer) => self.parenthesize(&head, &[initializer]),
+                    None => format!("({})", head),
+                }
+            }
+            Stmt::Const { name, initializer } => {
+                self.parenthesize(&format!("const {}", name.lexeme), &[initializer])
+            }
+            Stmt::While {
+                condition, body, ..
+            } => format!(
+                "(while {} {})",
+                self.print(condition),
+                self.print_stmt(body)
+            ),
+            Stmt::Foreach {
+                name,
+                collection,
+                body,
+                ..
+            } => format!(
+                "(foreach {} {} {})",
+                name.lexeme,
+                self.print(collection),
+                self.print_stmt(body)
+            ),
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => {
+                let mut output = format!("(switch {}", self.print(discriminant));
+                for (value, body) in cases {
+                    output.push(' ');
+                    output.push_str(
+                        &self.parenthesize_stmts(&format!("case {}", self.print(value)), body),
+                    );
+                }
+                if let Some(body) = default {
+                    output.push(' ');
+                    output.push_str(&self.parenthesize_stmts("default", body));
+                }
+                output.push(')');
+
+                output
+            }
+        }
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut output = format!("({}", name);
+        for expr in exprs {
+            output.push(' ');
+            output.push_str(&self.print(expr));
+        }
+        output.push(')');
+
+        output
+    }
+
+    fn parenthesize_stmts(&self, name: &str, statements: &[Stmt]) -> String {
+        let mut output = format!("({}", name);
+        for statement in statements {
+            output.push(' ');
+            output.push_str(&self.print_stmt(statement));
+        }
+        output.push(')');
+
+        output
+    }
+
+    fn literal(&self, literal: &Literal) -> String {
+        match literal {
+            Literal::Int(value) => value.to_string(),
+            Literal::Number(value) => value.to_string(),
+            Literal::Str(value) => value.clone(),
+            Literal::Char(value) => value.to_string(),
+            Literal::Bool(value) => value.to_string(),
+            Literal::Nil => "nil".to_string(),
+        }
+    }
+}
+
+fn join_names(names: &[Token]) -> String {
+    let names: Vec<&str> = names.iter().map(|name| &*name.lexeme).collect();
+    names.join(" ")
+}
+
+/// The `op=` of a compound assignment, such as `+=`.
+fn compound(operator: &Option<Token>) -> Option<String> {
+    operator
+        .as_ref()
+        .map(|operator| format!("{}=", operator.lexeme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::token_type::TokenType;
+
+    fn print(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        AstPrinter.print(&expr)
+    }
+
+    #[test]
+    fn print_book_example() {
+        let expr = Expr::Binary {
+            left: Box::new(Expr::Unary {
+                operator: Token::new(TokenType::Minus, "-".into(), None, 1, 1, 0..1),
+                right: Box::new(Expr::Literal {
+                    value: Literal::Number(123.0),
+                    span: 1..4,
+                }),
+            }),
+            operator: Token::new(TokenType::Star, "*".into(), None, 1, 6, 5..6),
+            right: Box::new(Expr::Grouping {
+                expression: Box::new(Expr::Literal {
+                    value: Literal::Number(45.67),
+                    span: 8..13,
+                }),
+                span: 7..14,
+            }),
+        };
+
+        assert_eq!(AstPrinter.print(&expr), "(* (- 123) (group 45.67))");
+    }
+
+    #[test]
+    fn print_parsed_expressions() {
+        assert_eq!(print("-123 * (45.67)"), "(* (- 123) (group 45.67))");
+        assert_eq!(print("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(print("!true == nil"), "(== (! true) nil)");
+        assert_eq!(print("\"a\" < 'b'"), "(< a b)");
+        assert_eq!(print("a = b = 1"), "(= a (= b 1))");
+        assert_eq!(print("a or b and c"), "(or a (and b c))");
+        assert_eq!(print("a ? b : c ? d : e"), "(?: a b (?: c d e))");
+        assert_eq!(print("f(1, g())"), "(call f 1 (call g))");
+        assert_eq!(print("a.b = c.d"), "(set b a (get d c))");
+        assert_eq!(print("a.b *= 2"), "(set*= b a 2)");
+        assert_eq!(print("a[i] -= 2"), "(index-= a i 2)");
+        assert_eq!(print("fun (a, b) { return a; }"), "(fun (a b))");
+        assert_eq!(print("[1, [2]][0]"), "(index (array 1 (array 2)) 0)");
+        assert_eq!(print("{\"a\": 1}[k] = 2"), "(index= (map a 1) k 2)");
+    }
+
+    fn print_program(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let statements = Parser::new(tokens).parse().unwrap();
+        statements
+            .iter()
+            .map(|stmt| AstPrinter.print_stmt(stmt))
+            .collect()
+    }
+
+    #[test]
+    fn print_parsed_statements() {
+        assert_eq!(
+            print_program("var a = 1 + 2 * 3; print a; var b;"),
+            ["(var a (+ 1 (* 2 3)))", "(print a)", "(var b)"]
+        );
+        assert_eq!(
+            print_program("fun f(a, b) { return a; } if (x) f(1); else { write 2; }"),
+            [
+                "(fun f (a b) (return a))",
+                "(if x (; (call f 1)) (block (write 2)))"
+            ]
+        );
+        assert_eq!(
+            print_program("class B < A { m() { return; } } while (true) x = 1;"),
+            [
+                "(class B < A (fun m () (return)))",
+                "(while true (; (= x 1)))"
+            ]
+        );
+        assert_eq!(
+            print_program("switch (x) { case 1: print 1; print 2; default: }"),
+            ["(switch x (case 1 (print 1) (print 2)) (default))"]
+        );
+        assert_eq!(
+            print_program("foreach (x in [1]) print x;"),
+            ["(foreach x (array 1) (print x))"]
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::token::Token;
+use crate::value::Value;
+
+/// Anything that can be invoked with call syntax.
+pub trait Callable {
+    fn name(&self) -> &str;
+
+    /// The number of arguments the callable expects.
+    fn arity(&self) -> usize;
+
+    /// The fewest arguments the callable accepts, when trailing ones are
+    /// optional. It still accepts at most `arity`.
+    fn min_arity(&self) -> usize {
+        self.arity()
+    }
+
+    /// Invokes the callable. The interpreter has already checked that
+    /// the number of `arguments` is within the arity; `paren` locates any
+    /// error raised.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+}
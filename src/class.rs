@@ -0,0 +1,115 @@
+use crate::callable::Callable;
+use crate::function::LoxFunction;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::token::Token;
+use crate::value::Value;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A class declared in Lox code. Calling it constructs an instance.
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name: name.to_string(),
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks `name` up among this class's methods, then its superclass's.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+impl Callable for Rc<LoxClass> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A class takes whatever arguments its initializer does.
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let instance = LoxInstance::new(Rc::clone(self));
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(instance.clone())
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+/// An instance of a class. Clones share the same fields, so a field set
+/// through one reference is visible through every other.
+#[derive(Clone)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: Rc<RefCell<HashMap<String, Value>>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    /// Reads a field or, failing that, a method bound to this instance.
+    /// Fields shadow methods of the same name.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.fields.borrow().get(&*name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match self.class.find_method(&name.lexeme) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(self.clone())))),
+            None => Err(RuntimeError::new(
+                name,
+                &format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    /// Sets a field, creating it if the instance doesn't have one yet.
+    pub fn set(&self, name: &Token, value: Value) {
+        self.fields
+            .borrow_mut()
+            .insert(name.lexeme.to_string(), value);
+    }
+
+    /// Whether `self` and `other` are the same instance.
+    pub fn is(&self, other: &LoxInstance) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+}
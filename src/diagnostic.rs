@@ -0,0 +1,131 @@
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+/// A problem found in the source, at a line and column counted from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, column: usize, message: String) -> Self {
+        Diagnostic {
+            line,
+            column,
+            message,
+            severity: Severity::Error,
+        }
+    }
+
+    pub fn warning(line: usize, column: usize, message: String) -> Self {
+        Diagnostic {
+            line,
+            column,
+            message,
+            severity: Severity::Warning,
+        }
+    }
+
+    /// Formats the diagnostic for display, prefixed with the file name and
+    /// line (`foo.lox:3: Error: ...`), or just the line when the file is
+    /// unnamed (`[line 3] Error: ...`).
+    pub fn render(&self, file_name: Option<&str>) -> String {
+        match file_name {
+            Some(file_name) => format!(
+                "{}:{}: {}: {}",
+                file_name, self.line, self.severity, self.message
+            ),
+            None => format!("[line {}] {}: {}", self.line, self.severity, self.message),
+        }
+    }
+}
+
+/// Renders the source line `line` with a `^` under column `column`, both
+/// counted from 1, as a two-line snippet to print beneath an error message.
+/// `tab_width` is how many columns the scanner counted each tab as.
+///
+/// The padding before the caret repeats any tabs from the line itself, so
+/// the caret stays aligned however wide the terminal draws a tab.
+pub fn caret(source: &str, line: usize, column: usize, tab_width: usize) -> String {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let text = source.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let mut padding = String::new();
+    let mut current = 1;
+    for c in text.chars().chain(std::iter::repeat(' ')) {
+        if current >= column {
+            break;
+        }
+        if c == '\t' {
+            padding.push('\t');
+            current += tab_width;
+        } else {
+            padding.push(' ');
+            current += 1;
+        }
+    }
+
+    format!("{}\n{}^", text, padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn render_names_file_and_severity() {
+        let error = Diagnostic::error(3, 1, "Unterminated string.".to_string());
+        let warning = Diagnostic::warning(1, 4, "Odd.".to_string());
+
+        assert_eq!(
+            error.render(Some("foo.lox")),
+            "foo.lox:3: Error: Unterminated string."
+        );
+        assert_eq!(warning.render(None), "[line 1] Warning: Odd.");
+    }
+
+    #[test]
+    fn caret_points_at_column() {
+        let source = "var a = 1;\nvar b = @;\n";
+
+        assert_eq!(caret(source, 2, 9, 1), "var b = @;\n        ^");
+        assert_eq!(caret(source, 1, 1, 1), "var a = 1;\n^");
+    }
+
+    #[test]
+    fn caret_keeps_tabs_and_tolerates_missing_lines() {
+        assert_eq!(caret("\tx = @;", 1, 6, 1), "\tx = @;\n\t    ^");
+        assert_eq!(caret("var a;\n", 2, 1, 1), "\n^");
+    }
+
+    #[test]
+    fn caret_counts_tabs_as_the_scanner_does() {
+        let source = "\t\tx = @;";
+        let mut scanner = Scanner::new(source).with_tab_width(4);
+        scanner.scan_tokens();
+        let diagnostic = &scanner.diagnostics()[0];
+
+        assert_eq!(diagnostic.column, 13);
+        assert_eq!(
+            caret(source, diagnostic.line, diagnostic.column, 4),
+            "\t\tx = @;\n\t\t    ^"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use crate::interpreter::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// The variable bindings of a scope, chained to the scope that encloses it.
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    /// The names in `values` that were declared with `const`.
+    constants: HashSet<String>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Binds `name`, replacing any previous binding with the same name.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.constants.remove(name);
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Binds `name` like `define`, but so that it can't be assigned again.
+    pub fn define_constant(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+        self.constants.insert(name.to_string());
+    }
+
+    /// Looks `name` up in this scope, then in each enclosing scope in turn.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&*name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Rebinds an existing variable; assigning to an undeclared name is an
+    /// error.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if self.constants.contains(&*name.lexeme) {
+            return Err(constant_assignment(name));
+        }
+        if let Some(slot) = self.values.get_mut(&*name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Looks `name` up in this scope only.
+    pub fn get_local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    /// Reads `name` from the scope exactly `distance` hops out, as computed
+    /// by the resolver.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        if distance == 0 {
+            return self
+                .values
+                .get(&*name.lexeme)
+                .cloned()
+                .ok_or_else(|| undefined_variable(name));
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(distance - 1, name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Rebinds `name` in the scope exactly `distance` hops out.
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance == 0 {
+            if self.constants.contains(&*name.lexeme) {
+                return Err(constant_assignment(name));
+            }
+            return match self.values.get_mut(&*name.lexeme) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(undefined_variable(name)),
+            };
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> RuntimeError {
+    RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))
+}
+
+fn constant_assignment(name: &Token) -> RuntimeError {
+    RuntimeError::new(
+        name,
+        &format!("Cannot assign to constant '{}'.", name.lexeme),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_type::TokenType;
+
+    fn identifier(name: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            name.into(),
+            None,
+            1,
+            1,
+            0..name.len(),
+        )
+    }
+
+    #[test]
+    fn define_then_get() {
+        let mut environment = Environment::new();
+        environment.define("a", Value::Number(1.0));
+
+        assert_eq!(
+            environment.get(&identifier("a")).unwrap(),
+            Value::Number(1.0)
+        );
+    }
+
+    #[test]
+    fn get_undefined_variable_errors() {
+        let environment = Environment::new();
+        let error = environment.get(&identifier("b")).unwrap_err();
+
+        assert_eq!(error.message, "Undefined variable 'b'.");
+    }
+
+    #[test]
+    fn assign_requires_existing_binding() {
+        let mut environment = Environment::new();
+        assert!(environment.assign(&identifier("a"), Value::Nil).is_err());
+
+        environment.define("a", Value::Nil);
+        environment
+            .assign(&identifier("a"), Value::Bool(true))
+            .unwrap();
+        assert_eq!(
+            environment.get(&identifier("a")).unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn lookups_walk_the_enclosing_chain() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().define("a", Value::Number(1.0));
+        let mut inner = Environment::with_enclosing(Rc::clone(&outer));
+
+        assert_eq!(inner.get(&identifier("a")).unwrap(), Value::Number(1.0));
+
+        inner.assign(&identifier("a"), Value::Number(2.0)).unwrap();
+        assert_eq!(
+            outer.borrow().get(&identifier("a")).unwrap(),
+            Value::Number(2.0)
+        );
+    }
+
+    #[test]
+    fn constants_reject_assignment() {
+        let mut environment = Environment::new();
+        environment.define_constant("a", Value::Number(1.0));
+
+        let error = environment
+            .assign(&identifier("a"), Value::Nil)
+            .unwrap_err();
+        assert_eq!(error.message, "Cannot assign to constant 'a'.");
+        assert!(
+            environment
+                .assign_at(0, &identifier("a"), Value::Nil)
+                .is_err()
+        );
+
+        environment.define("a", Value::Nil);
+        assert!(
+            environment
+                .assign(&identifier("a"), Value::Bool(true))
+                .is_ok()
+        );
+    }
+}
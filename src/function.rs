@@ -0,0 +1,99 @@
+use crate::callable::Callable;
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, RuntimeError, RuntimeSignal};
+use crate::parser::Stmt;
+use crate::token::Token;
+use crate::value::Value;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A function declared in Lox code, together with the environment it was
+/// declared in.
+///
+/// The closure is shared, not copied: every function declared in a scope
+/// holds an `Rc` to that same scope, so assignments made through one closure
+/// are seen by the others and by the scope itself. A function stored in the
+/// environment it closes over (any named function, including recursive
+/// ones) forms an `Rc` cycle and is never freed. Lox has no way to observe
+/// that, and the interpreter's lifetime bounds the leak, so it is accepted
+/// rather than paying for weak references or a collector.
+pub struct LoxFunction {
+    name: Token,
+    params: Vec<Token>,
+    body: Rc<Vec<Stmt>>,
+    closure: Rc<RefCell<Environment>>,
+    /// Whether this is a class's `init` method, which always returns the
+    /// instance it was called on.
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        name: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            name,
+            params,
+            body,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method whose closure binds `this` to
+    /// `instance`.
+    pub fn bind(&self, instance: LoxInstance) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+
+        LoxFunction {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: Rc::clone(&self.body),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    fn this(&self) -> Value {
+        self.closure
+            .borrow()
+            .get_local("this")
+            .expect("initializer is bound to an instance")
+    }
+}
+
+impl Callable for LoxFunction {
+    fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        _paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        match interpreter.execute_block(&self.body, environment) {
+            Ok(()) | Err(RuntimeSignal::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Value::Nil),
+            Err(RuntimeSignal::Return(value)) => Ok(value),
+            Err(RuntimeSignal::Error(error)) => Err(error),
+        }
+    }
+}
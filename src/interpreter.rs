@@ -0,0 +1,2098 @@
+use crate::callable::Callable;
+use crate::class::LoxClass;
+use crate::environment::Environment;
+use crate::function::LoxFunction;
+use crate::native;
+use crate::parser::{Expr, ExprId, Stmt};
+use crate::rng::Rng;
+use crate::token::Token;
+use crate::token_type::TokenType;
+use crate::value::Value;
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: &str) -> Self {
+        RuntimeError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Why execution of a statement stopped early.
+#[derive(Debug)]
+pub enum RuntimeSignal {
+    Error(RuntimeError),
+    /// A `return` unwinding to the enclosing function call.
+    Return(Value),
+}
+
+impl From<RuntimeError> for RuntimeSignal {
+    fn from(error: RuntimeError) -> Self {
+        RuntimeSignal::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    /// The outermost scope, seeded with the native functions.
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    /// How many scopes out each resolved local reference lives. Anything
+    /// missing is a global.
+    locals: HashMap<ExprId, usize>,
+    /// Where `print` writes.
+    output: Box<dyn Write>,
+    /// How many calls are in progress, and how many may be before a call
+    /// fails with a stack overflow.
+    call_depth: usize,
+    max_call_depth: usize,
+    /// How deeply expressions and statements are nested in evaluation,
+    /// counting those of enclosing calls too, and how deeply they may be
+    /// before evaluation fails with a stack overflow.
+    nesting_depth: usize,
+    max_nesting_depth: usize,
+    /// How many steps have run, and how many may before execution is cut
+    /// short. `None` means no limit.
+    steps: u64,
+    step_budget: Option<u64>,
+    /// The generator behind `random`, reseeded by `randomSeed`.
+    rng: Rng,
+}
+
+/// How deeply calls may nest by default. Each Lox call takes several Rust
+/// stack frames, which in a debug build add up to tens of kilobytes, so a
+/// thread running Lox needs a stack of some 32 MiB to reach this depth
+/// safely; `main` gives its interpreter thread one.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// How deeply expressions and statements may nest in evaluation by
+/// default, such as in `1 + 1 + ... + 1` with many terms. A call at the
+/// default call depth already nests a few thousand deep. In a debug build
+/// each level takes up to 20 KiB of stack, so this needs some 256 MiB.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 10_000;
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::with_output(Box::new(io::stdout()))
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::default()
+    }
+
+    /// Creates an interpreter whose `print` output goes to `output` instead
+    /// of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let mut globals = Environment::new();
+        native::define_natives(&mut globals);
+        let globals = Rc::new(RefCell::new(globals));
+
+        Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            output,
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            nesting_depth: 0,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            steps: 0,
+            step_budget: None,
+            rng: Rng::from_time(),
+        }
+    }
+
+    /// Sets how deeply calls may nest before one fails with a
+    /// `"Stack overflow."` runtime error.
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Sets how deeply expressions and statements may nest in evaluation
+    /// before one fails with a `"Stack overflow."` runtime error.
+    pub fn with_max_nesting_depth(mut self, max_nesting_depth: usize) -> Self {
+        self.max_nesting_depth = max_nesting_depth;
+        self
+    }
+
+    /// Limits how many steps may run from now on before execution fails
+    /// with an `"Execution budget exceeded."` runtime error, or lifts the
+    /// limit with `None`, the default. A step is one loop iteration or one
+    /// call, so that any program that runs forever eventually stops.
+    pub fn set_step_budget(&mut self, step_budget: Option<u64>) {
+        self.steps = 0;
+        self.step_budget = step_budget;
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(RuntimeSignal::Error(error)) => return Err(error),
+                // The parser only accepts `return` inside function bodies.
+                Err(RuntimeSignal::Return(_)) => unreachable!("return outside of a function"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates a lone expression, as typed at the REPL.
+    pub fn interpret_expression(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.evaluate(expr)
+    }
+
+    /// Records that the variable expression `id` refers to a local `depth`
+    /// scopes out from where it appears.
+    pub(crate) fn resolve(&mut self, id: ExprId, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeSignal> {
+        self.nest(stmt_token(stmt))?;
+        let result = self.execute_unnested(stmt);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    fn execute_unnested(&mut self, stmt: &Stmt) -> Result<(), RuntimeSignal> {
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                self.write_output(&format!("{}\n", value.to_display_string()));
+            }
+            Stmt::Write(expr) => {
+                let value = self.evaluate(expr)?;
+                self.write_output(&value.to_display_string());
+            }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                return Err(RuntimeSignal::Return(value));
+            }
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+                self.environment.borrow_mut().define(&name.lexeme, value);
+            }
+            Stmt::Const { name, initializer } => {
+                let value = self.evaluate(initializer)?;
+                self.environment
+                    .borrow_mut()
+                    .define_constant(&name.lexeme, value);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.execute(then_branch)?;
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+            } => {
+                while self.evaluate(condition)?.is_truthy() {
+                    self.take_step(keyword)?;
+                    self.execute(body)?;
+                }
+            }
+            Stmt::Function { name, params, body } => {
+                let function = LoxFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    Rc::clone(body),
+                    Rc::clone(&self.environment),
+                    false,
+                );
+                self.environment
+                    .borrow_mut()
+                    .define(&name.lexeme, Value::Callable(Rc::new(function)));
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(
+                        superclass @ Expr::Variable {
+                            name: superclass_name,
+                            ..
+                        },
+                    ) => match self.evaluate(superclass)? {
+                        Value::Class(class) => Some(class),
+                        _ => {
+                            return Err(RuntimeError::new(
+                                superclass_name,
+                                "Superclass must be a class.",
+                            )
+                            .into());
+                        }
+                    },
+                    _ => None,
+                };
+
+                // Methods of a subclass close over a scope binding `super`.
+                let enclosing = Rc::clone(&self.environment);
+                if let Some(superclass) = &superclass {
+                    let mut environment = Environment::with_enclosing(Rc::clone(&enclosing));
+                    environment.define("super", Value::Class(Rc::clone(superclass)));
+                    self.environment = Rc::new(RefCell::new(environment));
+                }
+
+                let mut functions = HashMap::new();
+                for method in methods {
+                    if let Stmt::Function { name, params, body } = method {
+                        let function = LoxFunction::new(
+                            name.clone(),
+                            params.clone(),
+                            Rc::clone(body),
+                            Rc::clone(&self.environment),
+                            &*name.lexeme == "init",
+                        );
+                        functions.insert(name.lexeme.to_string(), Rc::new(function));
+                    }
+                }
+
+                self.environment = enclosing;
+                let class = LoxClass::new(&name.lexeme, superclass, functions);
+                self.environment
+                    .borrow_mut()
+                    .define(&name.lexeme, Value::Class(Rc::new(class)));
+            }
+            Stmt::Block(statements) => {
+                let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+                self.execute_block(statements, environment)?;
+            }
+            Stmt::Foreach {
+                keyword,
+                name,
+                collection,
+                body,
+            } => {
+                // Iterates over a snapshot, so changing the collection in the
+                // body doesn't change which items are visited. Map keys come
+                // in sorted order so that iteration is deterministic.
+                let items: Vec<Value> = match self.evaluate(collection)? {
+                    Value::Array(elements) => elements.borrow().clone(),
+                    Value::Map(map) => {
+                        let mut keys: Vec<String> = map.borrow().keys().cloned().collect();
+                        keys.sort();
+                        keys.into_iter().map(Value::Str).collect()
+                    }
+                    _ => {
+                        return Err(RuntimeError::new(
+                            keyword,
+                            "Can only iterate over arrays and maps.",
+                        )
+                        .into());
+                    }
+                };
+                for item in items {
+                    self.take_step(keyword)?;
+                    let mut environment = Environment::with_enclosing(Rc::clone(&self.environment));
+                    environment.define(&name.lexeme, item);
+                    self.execute_block(std::slice::from_ref(body), environment)?;
+                }
+            }
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => {
+                let discriminant = self.evaluate(discriminant)?;
+                let mut chosen = default.as_ref();
+                // Case values are evaluated in order, only until one matches.
+                for (value, body) in cases {
+                    if is_equal(&discriminant, &self.evaluate(value)?) {
+                        chosen = Some(body);
+                        break;
+                    }
+                }
+                if let Some(body) = chosen {
+                    let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+                    self.execute_block(body, environment)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `statements` in `environment`, restoring the current scope
+    /// afterwards even if one of them fails.
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), RuntimeSignal> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.environment = previous;
+        result
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.nest(expr_token(expr))?;
+        let result = self.evaluate_unnested(expr);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    fn evaluate_unnested(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal { value, .. } => Ok(Value::from(value)),
+            Expr::Grouping { expression, .. } => self.evaluate(expression),
+            Expr::Variable { id, name } => self.look_up_variable(*id, name),
+            Expr::Assign { id, name, value } => {
+                let value = self.evaluate(value)?;
+                match self.locals.get(id) {
+                    Some(&distance) => {
+                        self.environment
+                            .borrow_mut()
+                            .assign_at(distance, name, value.clone())?;
+                    }
+                    None => self.globals.borrow_mut().assign(name, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(then_expr)
+                } else {
+                    self.evaluate(else_expr)
+                }
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+
+                // Short-circuit, yielding the operand that decided the result.
+                if operator.token_type == TokenType::Or {
+                    if left.is_truthy() {
+                        return Ok(left);
+                    }
+                } else if !left.is_truthy() {
+                    return Ok(left);
+                }
+
+                self.evaluate(right)
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                // `push` and `pop` are called on an array directly rather than
+                // fetched as values, so the object is evaluated here, once.
+                let callee = match callee.as_ref() {
+                    Expr::Get { object, name } => {
+                        let object = self.evaluate(object)?;
+                        if let Value::Array(elements) = &object
+                            && matches!(&*name.lexeme, "push" | "pop")
+                        {
+                            let mut values = Vec::with_capacity(arguments.len());
+                            for argument in arguments {
+                                values.push(self.evaluate(argument)?);
+                            }
+                            return array_method(elements, name, paren, values);
+                        }
+                        get_property(object, name)?
+                    }
+                    callee => self.evaluate(callee)?,
+                };
+
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(self.evaluate(argument)?);
+                }
+
+                match callee {
+                    Value::Callable(callable) => self.call(callable.as_ref(), paren, values),
+                    Value::Class(class) => self.call(&class, paren, values),
+                    _ => Err(RuntimeError::new(
+                        paren,
+                        "Can only call functions and classes.",
+                    )),
+                }
+            }
+            Expr::This { id, keyword } => self.look_up_variable(*id, keyword),
+            Expr::Super {
+                id,
+                keyword,
+                method,
+            } => {
+                let distance = self.locals[id];
+                let environment = self.environment.borrow();
+                let Value::Class(superclass) = environment.get_at(distance, keyword)? else {
+                    unreachable!("'super' is always bound to a class");
+                };
+                // `this` lives in the scope just inside the one binding `super`.
+                let this = Token::new(
+                    TokenType::This,
+                    Rc::from("this"),
+                    None,
+                    keyword.line,
+                    keyword.column,
+                    keyword.span.clone(),
+                );
+                let Value::Instance(instance) = environment.get_at(distance - 1, &this)? else {
+                    unreachable!("'this' is always bound to an instance");
+                };
+
+                match superclass.find_method(&method.lexeme) {
+                    Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance)))),
+                    None => Err(RuntimeError::new(
+                        method,
+                        &format!("Undefined property '{}'.", method.lexeme),
+                    )),
+                }
+            }
+            Expr::Function {
+                keyword,
+                params,
+                body,
+                ..
+            } => {
+                let name = Token::new(
+                    TokenType::Fun,
+                    Rc::from("anonymous"),
+                    None,
+                    keyword.line,
+                    keyword.column,
+                    keyword.span.clone(),
+                );
+                let function = LoxFunction::new(
+                    name,
+                    params.clone(),
+                    Rc::clone(body),
+                    Rc::clone(&self.environment),
+                    false,
+                );
+                Ok(Value::Callable(Rc::new(function)))
+            }
+            Expr::Array { elements, .. } => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::Array(Rc::new(RefCell::new(values))))
+            }
+            Expr::Map { brace, entries, .. } => {
+                let mut map = HashMap::new();
+                for (key, value) in entries {
+                    let key = map_key(brace, &self.evaluate(key)?)?;
+                    map.insert(key, self.evaluate(value)?);
+                }
+                Ok(Value::Map(Rc::new(RefCell::new(map))))
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                get_index(&object, bracket, &index)
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                operator,
+                value,
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let value = match operator {
+                    Some(operator) => {
+                        let current = get_index(&object, bracket, &index)?;
+                        binary(operator, current, self.evaluate(value)?)?
+                    }
+                    None => self.evaluate(value)?,
+                };
+                set_index(&object, bracket, &index, value.clone())?;
+                Ok(value)
+            }
+            Expr::Get { object, name } => get_property(self.evaluate(object)?, name),
+            Expr::Set {
+                object,
+                name,
+                operator,
+                value,
+            } => {
+                let Value::Instance(instance) = self.evaluate(object)? else {
+                    return Err(RuntimeError::new(name, "Only instances have fields."));
+                };
+                let value = match operator {
+                    Some(operator) => {
+                        let current = instance.get(name)?;
+                        binary(operator, current, self.evaluate(value)?)?
+                    }
+                    None => self.evaluate(value)?,
+                };
+                instance.set(name, value.clone());
+                Ok(value)
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.evaluate(right)?;
+
+                match operator.token_type {
+                    TokenType::Minus => match right {
+                        Value::Int(right) => Ok(right
+                            .checked_neg()
+                            .map_or(Value::Number(-(right as f64)), Value::Int)),
+                        _ => Ok(Value::Number(-number_operand(operator, &right)?)),
+                    },
+                    TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+                    _ => Err(RuntimeError::new(operator, "Unknown unary operator.")),
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(operator, left, right)
+            }
+        }
+    }
+}
+
+impl Interpreter {
+    /// Writes program output, flushing so that partial lines from `write`
+    /// show up immediately. Like `println!`, a failure to write is fatal.
+    fn write_output(&mut self, text: &str) {
+        self.output
+            .write_all(text.as_bytes())
+            .and_then(|()| self.output.flush())
+            .expect("failed to write output");
+    }
+
+    /// Enters one more level of nesting, failing at `token` if that is too
+    /// deep. Without a token the level is counted but never fails; see
+    /// `expr_token`.
+    fn nest(&mut self, token: Option<&Token>) -> Result<(), RuntimeError> {
+        if self.nesting_depth >= self.max_nesting_depth
+            && let Some(token) = token
+        {
+            return Err(RuntimeError::new(token, "Stack overflow."));
+        }
+        self.nesting_depth += 1;
+        Ok(())
+    }
+
+    /// Counts a step against the budget, failing at `token` if none is left.
+    fn take_step(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        if self.step_budget.is_some_and(|budget| self.steps >= budget) {
+            return Err(RuntimeError::new(token, "Execution budget exceeded."));
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn look_up_variable(&self, id: ExprId, name: &Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(&distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn call(
+        &mut self,
+        callable: &dyn Callable,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let (min, max) = (callable.min_arity(), callable.arity());
+        if arguments.len() < min || arguments.len() > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(RuntimeError::new(
+                paren,
+                &format!(
+                    "Expected {} arguments but got {}.",
+                    expected,
+                    arguments.len()
+                ),
+            ));
+        }
+
+        self.take_step(paren)?;
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new(paren, "Stack overflow."));
+        }
+        self.call_depth += 1;
+        let result = callable.call(self, paren, arguments);
+        self.call_depth -= 1;
+
+        result
+    }
+}
+
+/// The token to report an expression nested too deeply at. Groupings and
+/// array literals have none, but only nest as deeply as the scanner allows
+/// brackets to; literals and function expressions contain no other
+/// expressions to evaluate.
+fn expr_token(expr: &Expr) -> Option<&Token> {
+    match expr {
+        Expr::Binary { operator, .. }
+        | Expr::Unary { operator, .. }
+        | Expr::Logical { operator, .. } => Some(operator),
+        Expr::Variable { name, .. }
+        | Expr::Assign { name, .. }
+        | Expr::Get { name, .. }
+        | Expr::Set { name, .. } => Some(name),
+        Expr::Ternary { question, .. } => Some(question),
+        Expr::Call { paren, .. } => Some(paren),
+        Expr::Index { bracket, .. } | Expr::IndexSet { bracket, .. } => Some(bracket),
+        Expr::Map { brace, .. } => Some(brace),
+        Expr::This { keyword, .. } | Expr::Super { keyword, .. } => Some(keyword),
+        Expr::Grouping { .. }
+        | Expr::Literal { .. }
+        | Expr::Array { .. }
+        | Expr::Function { .. } => None,
+    }
+}
+
+/// The token to report a statement nested too deeply at. Blocks and
+/// `switch` bodies nest only as deeply as the scanner allows braces to, and
+/// the other statements without a token contain none but an expression.
+fn stmt_token(stmt: &Stmt) -> Option<&Token> {
+    match stmt {
+        Stmt::If { keyword, .. }
+        | Stmt::While { keyword, .. }
+        | Stmt::Foreach { keyword, .. }
+        | Stmt::Return { keyword, .. } => Some(keyword),
+        Stmt::Class { name, .. }
+        | Stmt::Function { name, .. }
+        | Stmt::Var { name, .. }
+        | Stmt::Const { name, .. } => Some(name),
+        Stmt::Expression(_)
+        | Stmt::Print(_)
+        | Stmt::Write(_)
+        | Stmt::Block(_)
+        | Stmt::Switch { .. } => None,
+    }
+}
+
+/// Applies a binary operator other than `and` and `or` to its evaluated
+/// operands.
+fn binary(operator: &Token, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    match operator.token_type {
+        TokenType::EqualEqual => Ok(Value::Bool(is_equal(&left, &right))),
+        TokenType::BangEqual => Ok(Value::Bool(!is_equal(&left, &right))),
+        TokenType::Plus => match (left, right) {
+            (Value::Str(left), Value::Str(right)) => Ok(Value::Str(left + &right)),
+            (left, right) if left.as_f64().is_some() && right.as_f64().is_some() => {
+                arithmetic(operator, &left, &right)
+            }
+            _ => Err(RuntimeError::new(
+                operator,
+                "Operands must be two numbers or two strings.",
+            )),
+        },
+        TokenType::Ampersand
+        | TokenType::Pipe
+        | TokenType::Caret
+        | TokenType::LessLess
+        | TokenType::GreaterGreater => bitwise(operator, &left, &right),
+        TokenType::Minus
+        | TokenType::Star
+        | TokenType::StarStar
+        | TokenType::Slash
+        | TokenType::Percent => arithmetic(operator, &left, &right),
+        _ => {
+            // Two `Int`s compare exactly, without rounding to floats.
+            let ordering = match (&left, &right) {
+                (Value::Int(left), Value::Int(right)) => Some(left.cmp(right)),
+                _ => {
+                    let (left, right) = number_operands(operator, &left, &right)?;
+                    left.partial_cmp(&right)
+                }
+            };
+
+            Ok(Value::Bool(match operator.token_type {
+                TokenType::Greater => ordering.is_some_and(Ordering::is_gt),
+                TokenType::GreaterEqual => ordering.is_some_and(Ordering::is_ge),
+                TokenType::Less => ordering.is_some_and(Ordering::is_lt),
+                TokenType::LessEqual => ordering.is_some_and(Ordering::is_le),
+                _ => {
+                    return Err(RuntimeError::new(operator, "Unknown binary operator."));
+                }
+            }))
+        }
+    }
+}
+
+/// Lox arithmetic on two numbers, each an `Int` or a float.
+///
+/// When both operands are `Int`s, `+`, `-`, `*`, and `%` give an `Int`, as
+/// does `**` with a non-negative exponent. If the exact result does not fit
+/// in an `i64`, it is computed as a float instead, so `Int` arithmetic never
+/// wraps. `/` always gives a float, so `3 / 2` is `1.5` and `4 / 2` is
+/// `2.0`. If either operand is a float, both are converted to floats and so
+/// is the result.
+fn arithmetic(operator: &Token, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+    if let (Value::Int(left), Value::Int(right)) = (left, right) {
+        let (left, right) = (*left, *right);
+        let exact = match operator.token_type {
+            TokenType::Plus => left.checked_add(right),
+            TokenType::Minus => left.checked_sub(right),
+            TokenType::Star => left.checked_mul(right),
+            TokenType::StarStar => u32::try_from(right)
+                .ok()
+                .and_then(|exponent| left.checked_pow(exponent)),
+            TokenType::Percent if right == 0 => {
+                return Err(RuntimeError::new(operator, "Division by zero."));
+            }
+            TokenType::Percent => {
+                // Only `i64::MIN % -1` overflows, and its remainder is
+                // exactly 0, which is what `wrapping_rem` gives.
+                let remainder = left.wrapping_rem(right);
+                Some(if remainder != 0 && (remainder < 0) != (right < 0) {
+                    remainder + right
+                } else {
+                    remainder
+                })
+            }
+            _ => None,
+        };
+        if let Some(result) = exact {
+            return Ok(Value::Int(result));
+        }
+    }
+
+    let (left, right) = number_operands(operator, left, right)?;
+    let divides = matches!(operator.token_type, TokenType::Slash | TokenType::Percent);
+    if divides && right == 0.0 {
+        return Err(RuntimeError::new(operator, "Division by zero."));
+    }
+
+    Ok(Value::Number(match operator.token_type {
+        TokenType::Plus => left + right,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::StarStar => left.powf(right),
+        TokenType::Slash => left / right,
+        TokenType::Percent => floored_modulo(left, right),
+        _ => return Err(RuntimeError::new(operator, "Unknown binary operator.")),
+    }))
+}
+
+/// Lox `%`, a floored modulus: the result takes the sign of the divisor, so
+/// `-7 % 3` is `2` and `7 % -3` is `-2`, as in Python or Ruby. Rust's `%`
+/// truncates instead and would give `-1` and `1`.
+fn floored_modulo(left: f64, right: f64) -> f64 {
+    let remainder = left % right;
+    if remainder != 0.0 && (remainder < 0.0) != (right < 0.0) {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+/// Reads `object[index]` from an array or map.
+fn get_index(object: &Value, bracket: &Token, index: &Value) -> Result<Value, RuntimeError> {
+    match object {
+        Value::Array(elements) => {
+            let elements = elements.borrow();
+            let position = array_position(bracket, index, elements.len())?;
+            Ok(elements[position].clone())
+        }
+        Value::Map(map) => {
+            let key = map_key(bracket, index)?;
+            map.borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| RuntimeError::new(bracket, &format!("Undefined key '{}'.", key)))
+        }
+        _ => Err(RuntimeError::new(
+            bracket,
+            "Only arrays and maps can be indexed.",
+        )),
+    }
+}
+
+/// Writes `object[index]`. Arrays can only overwrite existing elements;
+/// maps gain any key that is new.
+fn set_index(
+    object: &Value,
+    bracket: &Token,
+    index: &Value,
+    value: Value,
+) -> Result<(), RuntimeError> {
+    match object {
+        Value::Array(elements) => {
+            let mut elements = elements.borrow_mut();
+            let position = array_position(bracket, index, elements.len())?;
+            elements[position] = value;
+        }
+        Value::Map(map) => {
+            let key = map_key(bracket, index)?;
+            map.borrow_mut().insert(key, value);
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                bracket,
+                "Only arrays and maps can be indexed.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `index` is a whole number within an array of `len` elements.
+fn array_position(bracket: &Token, index: &Value, len: usize) -> Result<usize, RuntimeError> {
+    let Some(position) = index.as_i64() else {
+        return Err(RuntimeError::new(
+            bracket,
+            "Array index must be an integer.",
+        ));
+    };
+    usize::try_from(position)
+        .ok()
+        .filter(|&position| position < len)
+        .ok_or_else(|| RuntimeError::new(bracket, "Array index out of bounds."))
+}
+
+fn get_property(object: Value, name: &Token) -> Result<Value, RuntimeError> {
+    match object {
+        Value::Instance(instance) => instance.get(name),
+        value @ (Value::Str(_) | Value::Array(_)) => builtin_property(&value, name),
+        _ => Err(RuntimeError::new(
+            name,
+            "Only instances, strings, and arrays have properties.",
+        )),
+    }
+}
+
+/// Calls `push` or `pop` on an array. `push` appends its argument and
+/// returns the new length; `pop` removes and returns the last element.
+fn array_method(
+    elements: &RefCell<Vec<Value>>,
+    name: &Token,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let arity = usize::from(&*name.lexeme == "push");
+    if arguments.len() != arity {
+        return Err(RuntimeError::new(
+            paren,
+            &format!("Expected {} arguments but got {}.", arity, arguments.len()),
+        ));
+    }
+
+    let mut elements = elements.borrow_mut();
+    match &*name.lexeme {
+        "push" => {
+            elements.extend(arguments);
+            Ok(Value::Int(elements.len() as i64))
+        }
+        _ => elements
+            .pop()
+            .ok_or_else(|| RuntimeError::new(name, "Can't pop from an empty array.")),
+    }
+}
+
+/// Looks up a property of a string or array. Both have a `length`,
+/// counted in characters for a string.
+fn builtin_property(value: &Value, name: &Token) -> Result<Value, RuntimeError> {
+    let length = match (value, &*name.lexeme) {
+        (Value::Str(string), "length") => string.chars().count(),
+        (Value::Array(elements), "length") => elements.borrow().len(),
+        _ => {
+            return Err(RuntimeError::new(
+                name,
+                &format!("Undefined property '{}'.", name.lexeme),
+            ));
+        }
+    };
+    Ok(Value::Int(length as i64))
+}
+
+fn map_key(token: &Token, key: &Value) -> Result<String, RuntimeError> {
+    match key {
+        Value::Str(key) => Ok(key.clone()),
+        _ => Err(RuntimeError::new(token, "Map keys must be strings.")),
+    }
+}
+
+/// Lox `==`. Values of different types are never equal, so there is no
+/// coercion: `0 == false` and `nil == false` are both false. Numbers follow
+/// IEEE 754, so `NaN` is not equal even to itself, and an `Int` equals the
+/// float with exactly the same value, so `1 == 1.0`. Everything else
+/// follows `Value`'s `PartialEq`, so functions, classes, and instances are
+/// equal only to themselves.
+fn is_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Int(int), Value::Number(float)) | (Value::Number(float), Value::Int(int)) => {
+            // `as` saturates, so floats outside the `i64` range are ruled
+            // out first. Comparing as floats instead would round `int`.
+            let range = i64::MIN as f64..-(i64::MIN as f64);
+            float.fract() == 0.0 && range.contains(float) && *float as i64 == *int
+        }
+        _ => left == right,
+    }
+}
+
+fn number_operand(operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+    operand
+        .as_f64()
+        .ok_or_else(|| RuntimeError::new(operator, "Operand must be a number."))
+}
+
+/// Applies a bitwise operator to two whole numbers, computing in `i64`.
+fn bitwise(operator: &Token, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+    number_operands(operator, left, right)?;
+    let (Some(left), Some(right)) = (left.as_i64(), right.as_i64()) else {
+        return Err(RuntimeError::new(operator, "Operands must be integers."));
+    };
+
+    let shift = || {
+        u32::try_from(right)
+            .ok()
+            .filter(|&shift| shift < i64::BITS)
+            .ok_or_else(|| RuntimeError::new(operator, "Shift amount must be between 0 and 63."))
+    };
+    let result = match operator.token_type {
+        TokenType::Ampersand => left & right,
+        TokenType::Pipe => left | right,
+        TokenType::Caret => left ^ right,
+        TokenType::LessLess => left << shift()?,
+        _ => left >> shift()?,
+    };
+
+    Ok(Value::Int(result))
+}
+
+fn number_operands(
+    operator: &Token,
+    left: &Value,
+    right: &Value,
+) -> Result<(f64, f64), RuntimeError> {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(left), Some(right)) => Ok((left, right)),
+        _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        Interpreter::new().evaluate(&expr)
+    }
+
+    fn run(source: &str) -> Result<(), RuntimeError> {
+        run_in(&mut Interpreter::new(), source)
+    }
+
+    fn run_in(interpreter: &mut Interpreter, source: &str) -> Result<(), RuntimeError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Resolver::new(interpreter).resolve(&statements).unwrap();
+        interpreter.interpret(&statements)
+    }
+
+    /// A `Write` whose bytes can still be read after the interpreter that
+    /// owns it has written them.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `source` and returns everything it printed.
+    fn output(source: &str) -> String {
+        let buffer = SharedBuffer::default();
+        let mut interpreter = Interpreter::with_output(Box::new(buffer.clone()));
+        run_in(&mut interpreter, source).unwrap();
+        String::from_utf8(buffer.0.take()).unwrap()
+    }
+
+    /// Runs `source` and returns the value it left in the global `name`.
+    fn global(source: &str, name: &str) -> Value {
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+        get(&interpreter, name)
+    }
+
+    fn get(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter
+            .environment
+            .borrow()
+            .get(&variable(name))
+            .unwrap()
+    }
+
+    fn variable(name: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            name.into(),
+            None,
+            1,
+            1,
+            0..name.len(),
+        )
+    }
+
+    #[test]
+    fn evaluate_arithmetic() {
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), Value::Int(9));
+        assert_eq!(evaluate("10 - 4 / 2").unwrap(), Value::Number(8.0));
+        assert_eq!(evaluate("-(3)").unwrap(), Value::Int(-3));
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(evaluate("2 ** 3 ** 2").unwrap(), Value::Int(512));
+        assert_eq!(evaluate("2 * 3 ** 2").unwrap(), Value::Int(18));
+        assert_eq!(evaluate("-2 ** 2").unwrap(), Value::Int(-4));
+        assert_eq!(evaluate("2 ** -1").unwrap(), Value::Number(0.5));
+
+        let error = evaluate("2 ** \"a\"").unwrap_err();
+        assert_eq!(error.message, "Operands must be numbers.");
+    }
+
+    #[test]
+    fn bitwise_operators_on_integers() {
+        assert_eq!(evaluate("6 & 3").unwrap(), Value::Int(2));
+        assert_eq!(evaluate("6 | 3").unwrap(), Value::Int(7));
+        assert_eq!(evaluate("6 ^ 3").unwrap(), Value::Int(5));
+        assert_eq!(evaluate("1 << 4").unwrap(), Value::Int(16));
+        assert_eq!(evaluate("-16 >> 2").unwrap(), Value::Int(-4));
+        // `&` binds tighter than `^`, which binds tighter than `|`, as in C.
+        assert_eq!(evaluate("1 | 2 ^ 3 & 1").unwrap(), Value::Int(3));
+        assert_eq!(evaluate("1 << 2 + 1").unwrap(), Value::Int(8));
+    }
+
+    #[test]
+    fn bitwise_operators_reject_non_integers() {
+        let error = evaluate("1.5 & 2").unwrap_err();
+        assert_eq!(error.message, "Operands must be integers.");
+        assert_eq!(
+            evaluate("1 | \"a\"").unwrap_err().message,
+            "Operands must be numbers."
+        );
+        assert_eq!(
+            evaluate("1 << 64").unwrap_err().message,
+            "Shift amount must be between 0 and 63."
+        );
+    }
+
+    #[test]
+    fn int_arithmetic_stays_int() {
+        assert!(matches!(evaluate("3 + 4").unwrap(), Value::Int(7)));
+        assert!(matches!(
+            evaluate("2 ** 10 - 3 * 5").unwrap(),
+            Value::Int(1009)
+        ));
+        assert!(matches!(evaluate("-7 % 3").unwrap(), Value::Int(2)));
+        assert!(matches!(evaluate("-(3)").unwrap(), Value::Int(-3)));
+        assert!(matches!(evaluate("6 & 3").unwrap(), Value::Int(2)));
+        assert!(matches!(
+            evaluate("(-9223372036854775807 - 1) % -1").unwrap(),
+            Value::Int(0)
+        ));
+    }
+
+    #[test]
+    fn int_arithmetic_promotes_to_float() {
+        assert!(matches!(evaluate("3 / 2").unwrap(), Value::Number(1.5)));
+        assert!(matches!(evaluate("4 / 2").unwrap(), Value::Number(2.0)));
+        assert!(matches!(evaluate("3 + 0.5").unwrap(), Value::Number(3.5)));
+        assert!(matches!(evaluate("2 ** -1").unwrap(), Value::Number(0.5)));
+        assert!(matches!(
+            evaluate("9223372036854775807 + 1").unwrap(),
+            Value::Number(9223372036854775808.0)
+        ));
+        assert_eq!(evaluate("1 == 1.0").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("2 < 2.5").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("1 % 0").unwrap_err().message, "Division by zero.");
+    }
+
+    #[test]
+    fn modulo_is_floored() {
+        assert_eq!(evaluate("7 % 3").unwrap(), Value::Int(1));
+        assert_eq!(evaluate("-7 % 3").unwrap(), Value::Int(2));
+        assert_eq!(evaluate("7 % -3").unwrap(), Value::Int(-2));
+        assert_eq!(evaluate("-6 % 3").unwrap(), Value::Int(0));
+        assert_eq!(evaluate("5.5 % 2").unwrap(), Value::Number(1.5));
+
+        let error = evaluate("7 % \"a\"").unwrap_err();
+        assert_eq!(error.message, "Operands must be numbers.");
+    }
+
+    #[test]
+    fn division_by_zero_errors() {
+        let error = evaluate("1 +\n1 / 0").unwrap_err();
+        assert_eq!(error.message, "Division by zero.");
+        assert_eq!(error.token.line, 2);
+
+        let error = evaluate("5 % -0").unwrap_err();
+        assert_eq!(error.message, "Division by zero.");
+
+        assert_eq!(evaluate("1 / 2").unwrap(), Value::Number(0.5));
+        assert_eq!(evaluate("-1 / 0.5").unwrap(), Value::Number(-2.0));
+    }
+
+    #[test]
+    fn evaluate_truthiness() {
+        assert_eq!(evaluate("!nil").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("!false").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("!0").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("!\"\"").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn evaluate_comparison_and_equality() {
+        assert_eq!(evaluate("1 < 2 == true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("2 <= 1").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("\"a\" != \"b\"").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("nil == false").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn equality_never_coerces_between_types() {
+        assert_eq!(evaluate("nil == nil").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("0 == false").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("0 == nil").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("\"1\" == 1").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("\"\" != nil").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("1.0 == 1").unwrap(), Value::Bool(true));
+        assert_eq!(
+            evaluate("\"ab\" == \"a\" + \"b\"").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(evaluate("clock == clock").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn nan_is_not_equal_to_itself() {
+        let nan = Value::Number(f64::NAN);
+
+        assert!(!is_equal(&nan, &nan));
+        assert!(is_equal(&Value::Number(0.0), &Value::Number(-0.0)));
+    }
+
+    #[test]
+    fn ints_and_floats_compare_exactly() {
+        assert!(is_equal(&Value::Int(3), &Value::Number(3.0)));
+        assert!(is_equal(&Value::Number(-0.0), &Value::Int(0)));
+        assert!(!is_equal(&Value::Int(3), &Value::Number(3.5)));
+        // 2^53 + 1 rounds to 2^53 as a float, but is not equal to it.
+        assert!(!is_equal(
+            &Value::Int(9007199254740993),
+            &Value::Number(9007199254740992.0)
+        ));
+        assert!(!is_equal(
+            &Value::Int(i64::MAX),
+            &Value::Number(9223372036854775808.0)
+        ));
+        assert!(is_equal(
+            &Value::Int(i64::MIN),
+            &Value::Number(i64::MIN as f64)
+        ));
+    }
+
+    #[test]
+    fn evaluate_type_mismatch_reports_operator_line() {
+        let error = evaluate("1 -\n\"a\"").unwrap_err();
+
+        assert_eq!(error.message, "Operands must be numbers.");
+        assert_eq!(error.token.line, 1);
+
+        let error = evaluate("-\"a\"").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+    }
+
+    #[test]
+    fn evaluate_plus_adds_or_concatenates() {
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\"").unwrap(),
+            Value::Str("foobar".to_string())
+        );
+        assert_eq!(evaluate("1 + 2").unwrap(), Value::Int(3));
+
+        let error = evaluate("\n\"a\" + 1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Operands must be two numbers or two strings."
+        );
+        assert_eq!(error.token.line, 2);
+    }
+
+    #[test]
+    fn print_writes_to_configured_output() {
+        assert_eq!(output("print 1; print 2;"), "1\n2\n");
+    }
+
+    #[test]
+    fn write_omits_newline() {
+        assert_eq!(output("write 1; write 2;"), "12");
+        assert_eq!(output("write \"a\"; print \"b\"; write nil;"), "ab\nnil");
+    }
+
+    #[test]
+    fn execute_print_statement() {
+        assert!(run("print 1 + 2;").is_ok());
+        assert_eq!(evaluate("1 + 2").unwrap().to_display_string(), "3");
+
+        let error = run("print -nil;").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+    }
+
+    #[test]
+    fn execute_var_declaration() {
+        assert_eq!(global("var a = 1; print a;", "a"), Value::Int(1));
+        assert_eq!(global("var a;", "a"), Value::Nil);
+        assert_eq!(global("var a = 1; var b = a + 1;", "b"), Value::Int(2));
+    }
+
+    #[test]
+    fn read_undeclared_variable_errors() {
+        let error = run("print a;\nprint b;\nvar b = 1;").unwrap_err();
+
+        assert_eq!(error.message, "Undefined variable 'a'.");
+        assert_eq!(error.token.line, 1);
+
+        let error = run("var a = 1;\nprint b;\nvar b = 2;").unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'b'.");
+        assert_eq!(error.token.line, 2);
+    }
+
+    #[test]
+    fn execute_assignment() {
+        assert_eq!(global("var a = 1; a = 5;", "a"), Value::Int(5));
+        assert_eq!(global("var a; var b = a = 2;", "b"), Value::Int(2));
+        assert_eq!(global("var a; var b; a = b = 3;", "a"), Value::Int(3));
+        assert_eq!(global("var a; var b; a = b = 3;", "b"), Value::Int(3));
+    }
+
+    #[test]
+    fn compound_assignment_updates_target() {
+        assert_eq!(global("var a = 1; a += 4;", "a"), Value::Int(5));
+        assert_eq!(
+            global("var a = 10; a -= 1; a *= 2; a /= 3; a %= 4;", "a"),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            global("var s = \"ab\"; s += \"c\";", "s"),
+            Value::Str("abc".to_string())
+        );
+
+        let source = "class C {} var c = C(); c.x = 1; c.x += 2; var r = c.x;";
+        assert_eq!(global(source, "r"), Value::Int(3));
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_target_once() {
+        let source = "
+            class C {}
+            var c = C();
+            c.x = 1;
+            var n = 0;
+            fun g() { n = n + 1; return c; }
+            g().x += 1;";
+        assert_eq!(global(source, "n"), Value::Int(1));
+
+        let source = "var a = [1, 2]; var i = 0; a[i = i + 1] += 10; var r = a[1];";
+        assert_eq!(global(source, "r"), Value::Int(12));
+        assert_eq!(global(source, "i"), Value::Int(1));
+    }
+
+    #[test]
+    fn assign_to_undeclared_variable_errors() {
+        let error = run("x = 1;").unwrap_err();
+
+        assert_eq!(error.message, "Undefined variable 'x'.");
+    }
+
+    #[test]
+    fn block_shadows_without_touching_outer_variable() {
+        let source = "var a = 1; var seen; { var a = 2; seen = a; }";
+
+        assert_eq!(global(source, "a"), Value::Int(1));
+        assert_eq!(global(source, "seen"), Value::Int(2));
+    }
+
+    #[test]
+    fn block_assigns_through_to_outer_variable() {
+        assert_eq!(global("var a = 1; { a = 3; }", "a"), Value::Int(3));
+    }
+
+    #[test]
+    fn scope_is_restored_after_runtime_error() {
+        let mut interpreter = Interpreter::new();
+        let error = run_in(&mut interpreter, "var a = 1; { var a = 2; -nil; }").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+
+        assert_eq!(get(&interpreter, "a"), Value::Int(1));
+    }
+
+    #[test]
+    fn switch_runs_matching_case_only() {
+        let source = "
+            fun pick(x) {
+                switch (x) {
+                    case 1: return \"one\";
+                    case \"two\": var r = \"two\"; return r;
+                    case 1: return \"unreachable\";
+                    default: return \"other\";
+                }
+            }
+            print pick(1); print pick(\"two\"); print pick(3);
+            switch (3) { case 1: print \"no\"; }
+            print \"done\";";
+        assert_eq!(output(source), "one\ntwo\nother\ndone\n");
+    }
+
+    #[test]
+    fn switch_does_not_fall_through() {
+        assert_eq!(
+            output(
+                "switch (1) { case 1: print \"a\"; case 2: print \"b\"; default: print \"c\"; }"
+            ),
+            "a\n"
+        );
+    }
+
+    #[test]
+    fn execute_if_branches() {
+        assert_eq!(
+            global("var a = 0; if (1 < 2) a = 1; else a = 2;", "a"),
+            Value::Int(1)
+        );
+        assert_eq!(
+            global("var a = 0; if (nil) a = 1; else a = 2;", "a"),
+            Value::Int(2)
+        );
+        assert_eq!(global("var a = 0; if (false) a = 1;", "a"), Value::Int(0));
+    }
+
+    #[test]
+    fn execute_dangling_else() {
+        // The else belongs to the inner if, so nothing runs when the outer
+        // condition is false.
+        let source = "var a = 0; if (false) if (true) a = 1; else a = 2;";
+        assert_eq!(global(source, "a"), Value::Int(0));
+
+        let source = "var a = 0; if (true) if (false) a = 1; else a = 2;";
+        assert_eq!(global(source, "a"), Value::Int(2));
+    }
+
+    #[test]
+    fn execute_for_loop() {
+        // Appending each index as a decimal digit records the visit order.
+        let source = "var seen = 0; for (var i = 0; i < 3; i = i + 1) seen = seen * 10 + i + 1;";
+        assert_eq!(global(source, "seen"), Value::Int(123));
+    }
+
+    #[test]
+    fn execute_while_loop() {
+        assert_eq!(
+            global("var a = 0; while (a < 5) a = a + 1;", "a"),
+            Value::Int(5)
+        );
+        assert_eq!(
+            global("var a = 0; while (false) a = 1;", "a"),
+            Value::Int(0)
+        );
+    }
+
+    #[test]
+    fn logical_operators_return_operand_values() {
+        assert_eq!(
+            evaluate("false or \"a\"").unwrap(),
+            Value::Str("a".to_string())
+        );
+        assert_eq!(evaluate("1 and 2").unwrap(), Value::Int(2));
+        assert_eq!(evaluate("nil or false").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("0 or 1").unwrap(), Value::Int(0));
+    }
+
+    #[test]
+    fn ternary_picks_branch_by_truthiness() {
+        assert_eq!(evaluate("true ? 1 : 2").unwrap(), Value::Int(1));
+        assert_eq!(evaluate("nil ? 1 : 2").unwrap(), Value::Int(2));
+        assert_eq!(
+            evaluate("0 ? \"a\" : false ? \"b\" : \"c\"").unwrap(),
+            Value::Str("a".to_string())
+        );
+        // Only the chosen branch runs.
+        assert_eq!(evaluate("false ? -nil : 3").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(evaluate("nil and undefined").unwrap(), Value::Nil);
+        assert_eq!(evaluate("true or undefined").unwrap(), Value::Bool(true));
+        assert_eq!(
+            global("var a = 0; nil and (a = 1); true or (a = 2);", "a"),
+            Value::Int(0)
+        );
+    }
+
+    #[test]
+    fn call_function_with_arguments() {
+        let source = "var sum; fun add(a, b) { sum = a + b; } add(1, 2);";
+        assert_eq!(global(source, "sum"), Value::Int(3));
+        assert_eq!(global("fun f() {} var r = f();", "r"), Value::Nil);
+    }
+
+    #[test]
+    fn return_exits_function_early() {
+        let source = "fun sign(n) { if (n < 0) return -1; if (n > 0) return 1; return 0; }
+            var a = sign(-5); var b = sign(3); var c = sign(0);";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(get(&interpreter, "a"), Value::Int(-1));
+        assert_eq!(get(&interpreter, "b"), Value::Int(1));
+        assert_eq!(get(&interpreter, "c"), Value::Int(0));
+    }
+
+    #[test]
+    fn bare_return_yields_nil() {
+        let source = "var after = false; fun f() { return; after = true; } var r = f();";
+        assert_eq!(global(source, "r"), Value::Nil);
+        assert_eq!(global(source, "after"), Value::Bool(false));
+    }
+
+    #[test]
+    fn return_unwinds_through_loops_and_blocks() {
+        let source = "fun find() { for (var i = 0; ; i = i + 1) { { if (i == 3) return i; } } }
+            var r = find();";
+        assert_eq!(global(source, "r"), Value::Int(3));
+    }
+
+    #[test]
+    fn call_with_wrong_arity_errors() {
+        let error = run("fun add(a, b) {}\nadd(1);").unwrap_err();
+
+        assert_eq!(error.message, "Expected 2 arguments but got 1.");
+        assert_eq!(error.token.line, 2);
+    }
+
+    #[test]
+    fn closure_captures_and_mutates_local() {
+        let source = "
+            fun makeCounter() {
+                var i = 0;
+                fun count() {
+                    i = i + 1;
+                    return i;
+                }
+                return count;
+            }
+            var counter = makeCounter();
+            var first = counter();
+            var second = counter();
+            var fresh = makeCounter()();";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(get(&interpreter, "first"), Value::Int(1));
+        assert_eq!(get(&interpreter, "second"), Value::Int(2));
+        assert_eq!(get(&interpreter, "fresh"), Value::Int(1));
+    }
+
+    #[test]
+    fn closure_keeps_binding_from_declaration() {
+        let source = "
+            var a = \"global\";
+            var first; var second;
+            {
+                fun show() { return a; }
+                first = show();
+                var a = \"block\";
+                second = show();
+            }";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(get(&interpreter, "first"), Value::Str("global".to_string()));
+        assert_eq!(
+            get(&interpreter, "second"),
+            Value::Str("global".to_string())
+        );
+    }
+
+    #[test]
+    fn closure_over_loop_variable_resolves() {
+        let source = "
+            var saved;
+            for (var i = 0; i < 3; i = i + 1) {
+                var j = i;
+                fun get() { return i * 10 + j; }
+                if (i == 1) saved = get;
+            }
+            var r = saved();";
+        // `i` is shared by the whole loop; `j` is fresh in each iteration.
+        assert_eq!(global(source, "r"), Value::Int(31));
+    }
+
+    #[test]
+    fn lambda_assigned_to_variable_can_be_called() {
+        let source = "
+            var offset = 10;
+            var add = fun (a) { return a + offset; };
+            var r = add(1);
+            var s = str(add);";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(get(&interpreter, "r"), Value::Int(11));
+        assert_eq!(
+            get(&interpreter, "s"),
+            Value::Str("<fn anonymous>".to_string())
+        );
+    }
+
+    #[test]
+    fn const_can_be_read_and_shadowed() {
+        let source = "
+            const a = 1;
+            var inner;
+            {
+                const a = 2;
+                inner = a;
+            }
+            fun f() { var a = 3; a = 4; return a; }
+            var r = a + inner + f();";
+        assert_eq!(global(source, "r"), Value::Int(7));
+    }
+
+    #[test]
+    fn assign_to_const_errors() {
+        let error = run("const a = 1;\na = 2;").unwrap_err();
+        assert_eq!(error.message, "Cannot assign to constant 'a'.");
+        assert_eq!(error.token.line, 2);
+
+        let error = run("{ const b = 1; b += 1; }").unwrap_err();
+        assert_eq!(error.message, "Cannot assign to constant 'b'.");
+    }
+
+    #[test]
+    fn instance_fields_can_be_set_and_read() {
+        let source = "class Pair {} var p = Pair(); p.x = 1; var q = p; q.y = p.x + 1;";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        let Value::Instance(p) = get(&interpreter, "p") else {
+            panic!("expected instance");
+        };
+        assert_eq!(p.get(&variable("x")).unwrap(), Value::Int(1));
+        assert_eq!(p.get(&variable("y")).unwrap(), Value::Int(2));
+        assert_eq!(get(&interpreter, "p").to_display_string(), "Pair instance");
+    }
+
+    #[test]
+    fn read_unset_field_errors() {
+        let error = run("class Pair {} var p = Pair(); print p.x;").unwrap_err();
+
+        assert_eq!(error.message, "Undefined property 'x'.");
+    }
+
+    #[test]
+    fn strings_and_arrays_have_length() {
+        assert_eq!(evaluate("\"hello\".length").unwrap(), Value::Int(5));
+        assert_eq!(evaluate("[1, 2, 3].length").unwrap(), Value::Int(3));
+        assert_eq!(evaluate("\"héllo\".length").unwrap(), Value::Int(5));
+        assert_eq!(evaluate("[].length").unwrap(), Value::Int(0));
+        assert_eq!(
+            evaluate("\"a\".size").unwrap_err().message,
+            "Undefined property 'size'."
+        );
+    }
+
+    #[test]
+    fn foreach_visits_array_elements_and_map_keys() {
+        let mut interpreter = Interpreter::new();
+        run_in(
+            &mut interpreter,
+            "var sum = 0; foreach (n in [1, 2, 3]) sum = sum + n;
+             var keys = \"\"; foreach (k in {\"b\": 1, \"a\": 2}) keys = keys + k;
+             var fs = []; foreach (n in [1, 2]) fs.push(fun () { return n; });
+             var first = fs[0]();",
+        )
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "sum"), Value::Int(6));
+        assert_eq!(get(&interpreter, "keys"), Value::Str("ab".to_string()));
+        // Each iteration binds a fresh variable.
+        assert_eq!(get(&interpreter, "first"), Value::Int(1));
+    }
+
+    #[test]
+    fn foreach_over_non_collection_errors() {
+        let error = run("foreach (x in 5) print x;").unwrap_err();
+        assert_eq!(error.message, "Can only iterate over arrays and maps.");
+    }
+
+    #[test]
+    fn unbounded_recursion_is_a_stack_overflow() {
+        // A test thread's default stack is too small for the default depth.
+        let thread = std::thread::Builder::new().stack_size(64 * 1024 * 1024);
+        let outcome = thread
+            .spawn(|| {
+                let mut interpreter = Interpreter::new();
+                let error = run_in(&mut interpreter, "fun f(n) { return f(n + 1); } f(0);");
+                // The depth unwinds with the error, so later calls still work.
+                let later = run_in(
+                    &mut interpreter,
+                    "fun g(n) { if (n > 0) g(n - 1); } g(900);",
+                );
+                (error.unwrap_err().message, later.is_ok())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(outcome, ("Stack overflow.".to_string(), true));
+    }
+
+    #[test]
+    fn max_call_depth_is_configurable() {
+        let mut interpreter = Interpreter::new().with_max_call_depth(3);
+        let source = "fun down(n) { if (n > 0) down(n - 1); }";
+        run_in(&mut interpreter, &format!("{} down(2);", source)).unwrap();
+
+        let error = run_in(&mut interpreter, "down(3);").unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+    }
+
+    #[test]
+    fn deeply_nested_expressions_are_a_stack_overflow() {
+        // Without the limit, this overflows even this stack in a debug build.
+        let thread = std::thread::Builder::new().stack_size(256 * 1024 * 1024);
+        let message = thread
+            .spawn(|| {
+                let source = format!("var a = {};", vec!["1"; 20_000].join(" + "));
+                run(&source).unwrap_err().message
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(message, "Stack overflow.");
+    }
+
+    #[test]
+    fn max_nesting_depth_is_configurable() {
+        let mut interpreter = Interpreter::new().with_max_nesting_depth(8);
+        run_in(&mut interpreter, "var a = 1 + 1 + 1;").unwrap();
+
+        let source = format!("var b = {}0;", "nil ? 1 : ".repeat(8));
+        let error = run_in(&mut interpreter, &source).unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+        let source = format!("{}a;", "if (true) ".repeat(8));
+        let error = run_in(&mut interpreter, &source).unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+
+        // The depth unwinds with the error, so later code still runs.
+        run_in(&mut interpreter, "var c = a + 1;").unwrap();
+    }
+
+    #[test]
+    fn step_budget_stops_infinite_loops() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_step_budget(Some(100));
+
+        let error = run_in(&mut interpreter, "while (true) {}").unwrap_err();
+        assert_eq!(error.message, "Execution budget exceeded.");
+        assert_eq!(&*error.token.lexeme, "while");
+
+        let error = run_in(&mut interpreter, "fun f() { return 1; } f();").unwrap_err();
+        assert_eq!(error.message, "Execution budget exceeded.");
+
+        interpreter.set_step_budget(Some(3));
+        run_in(&mut interpreter, "for (var i = 0; i < 3; i = i + 1) {}").unwrap();
+        interpreter.set_step_budget(None);
+        run_in(&mut interpreter, "for (var i = 0; i < 1000; i = i + 1) {}").unwrap();
+    }
+
+    #[test]
+    fn push_and_pop_mutate_arrays() {
+        let mut interpreter = Interpreter::new();
+        run_in(
+            &mut interpreter,
+            "var a = [1]; var b = a; var n = a.push(2); var last = b.pop(); a.push(3);",
+        )
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "n"), Value::Int(2));
+        assert_eq!(get(&interpreter, "last"), Value::Int(2));
+        assert_eq!(get(&interpreter, "a").to_display_string(), "[1, 3]");
+    }
+
+    #[test]
+    fn pop_from_empty_array_errors() {
+        let error = run("var a = [1]; a.pop(); a.pop();").unwrap_err();
+        assert_eq!(error.message, "Can't pop from an empty array.");
+
+        let error = run("[].push();").unwrap_err();
+        assert_eq!(error.message, "Expected 1 arguments but got 0.");
+    }
+
+    #[test]
+    fn property_access_on_non_instance_errors() {
+        let error = run("var n = 1; print n.length;").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Only instances, strings, and arrays have properties."
+        );
+        let error = run("print nil.length;").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Only instances, strings, and arrays have properties."
+        );
+
+        let error = run("var n = 1; n.x = 2;").unwrap_err();
+        assert_eq!(error.message, "Only instances have fields.");
+    }
+
+    #[test]
+    fn method_reads_this_field() {
+        let source = "
+            class Greeter { greet() { return \"hi \" + this.name; } }
+            var g = Greeter(); g.name = \"bob\";
+            var bound = g.greet;
+            var r = bound();";
+        assert_eq!(global(source, "r"), Value::Str("hi bob".to_string()));
+    }
+
+    #[test]
+    fn init_sets_fields_from_arguments() {
+        let source = "
+            class Point { init(x, y) { this.x = x; this.y = y; return; } }
+            var p = Point(1, 2);
+            var sum = p.x + p.y;
+            var again = p.init(3, 4) == p;";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(get(&interpreter, "sum"), Value::Int(3));
+        assert_eq!(get(&interpreter, "again"), Value::Bool(true));
+    }
+
+    #[test]
+    fn class_call_checks_initializer_arity() {
+        let error = run("class Point { init(x, y) {} } Point(1);").unwrap_err();
+
+        assert_eq!(error.message, "Expected 2 arguments but got 1.");
+    }
+
+    #[test]
+    fn subclass_inherits_and_calls_super() {
+        let source = "
+            class A { name() { return \"A\"; } describe() { return \"I am \" + this.name(); } }
+            class B < A { name() { return \"B+\" + super.name(); } }
+            var inherited = B().describe();
+            var overridden = B().name();";
+        let mut interpreter = Interpreter::new();
+        run_in(&mut interpreter, source).unwrap();
+
+        assert_eq!(
+            get(&interpreter, "inherited"),
+            Value::Str("I am B+A".to_string())
+        );
+        assert_eq!(
+            get(&interpreter, "overridden"),
+            Value::Str("B+A".to_string())
+        );
+    }
+
+    #[test]
+    fn inherit_from_non_class_errors() {
+        let error = run("var A = 1; class B < A {}").unwrap_err();
+
+        assert_eq!(error.message, "Superclass must be a class.");
+        assert_eq!(&*error.token.lexeme, "A");
+    }
+
+    #[test]
+    fn clock_returns_number() {
+        assert!(matches!(evaluate("clock()").unwrap(), Value::Number(_)));
+    }
+
+    #[test]
+    fn native_call_checks_arity() {
+        let error = evaluate("clock(1)").unwrap_err();
+
+        assert_eq!(error.message, "Expected 0 arguments but got 1.");
+    }
+
+    #[test]
+    fn seeded_random_is_reproducible() {
+        let mut interpreter = Interpreter::new();
+        run_in(
+            &mut interpreter,
+            "randomSeed(7); var a = random(); var b = random();
+             randomSeed(7); var c = random(); var d = random();",
+        )
+        .unwrap();
+
+        let (a, b) = (get(&interpreter, "a"), get(&interpreter, "b"));
+        assert_eq!(
+            (get(&interpreter, "c"), get(&interpreter, "d")),
+            (a.clone(), b.clone())
+        );
+        assert_ne!(a, b);
+        assert!(matches!(a, Value::Number(n) if (0.0..1.0).contains(&n)));
+
+        let error = evaluate("randomSeed(1.5)").unwrap_err();
+        assert_eq!(error.message, "randomSeed() expects an integer.");
+    }
+
+    #[test]
+    fn len_counts_characters() {
+        assert_eq!(evaluate("len(\"abc\")").unwrap(), Value::Int(3));
+        assert_eq!(evaluate("len(\"héllo\")").unwrap(), Value::Int(5));
+
+        let error = evaluate("len(3)").unwrap_err();
+        assert_eq!(error.message, "len() expects a string.");
+    }
+
+    #[test]
+    fn substr_slices_characters() {
+        assert_eq!(
+            evaluate("substr(\"abcdef\", 1, 4)").unwrap(),
+            Value::Str("bcd".to_string())
+        );
+        assert_eq!(
+            evaluate("substr(\"abc\", 3, 3)").unwrap(),
+            Value::Str("".to_string())
+        );
+    }
+
+    #[test]
+    fn substr_rejects_bad_ranges() {
+        let error = evaluate("\n substr(\"abcdef\", 2, 10)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Substring range 2..10 is out of bounds for a string of length 6."
+        );
+        assert_eq!(error.token.line, 2);
+
+        let error = evaluate("substr(\"abc\", 2, 1)").unwrap_err();
+        assert!(error.message.starts_with("Substring range 2..1"));
+
+        let error = evaluate("substr(\"abc\", 0.5, 1)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "substr() expects indices to be non-negative whole numbers."
+        );
+    }
+
+    #[test]
+    fn str_matches_print_output() {
+        assert_eq!(evaluate("str(42)").unwrap(), Value::Str("42".to_string()));
+        assert_eq!(evaluate("str(2.5)").unwrap(), Value::Str("2.5".to_string()));
+        assert_eq!(evaluate("str(nil)").unwrap(), Value::Str("nil".to_string()));
+        assert_eq!(
+            evaluate("str(clock)").unwrap(),
+            Value::Str("<fn clock>".to_string())
+        );
+    }
+
+    #[test]
+    fn num_parses_numeric_strings() {
+        assert_eq!(evaluate("num(\"3.5\")").unwrap(), Value::Number(3.5));
+        assert_eq!(evaluate("num(\" -2 \")").unwrap(), Value::Int(-2));
+        assert_eq!(evaluate("num(\"abc\")").unwrap(), Value::Nil);
+        assert_eq!(evaluate("num(\"inf\")").unwrap(), Value::Nil);
+
+        let error = evaluate("num(1)").unwrap_err();
+        assert_eq!(error.message, "num() expects a string.");
+    }
+
+    #[test]
+    fn assert_raises_with_message_when_falsey() {
+        assert_eq!(evaluate("assert(true)").unwrap(), Value::Nil);
+        assert_eq!(evaluate("assert(0, \"unused\")").unwrap(), Value::Nil);
+
+        let error = evaluate("assert(false, \"boom\")").unwrap_err();
+        assert_eq!(error.message, "boom");
+        assert_eq!(
+            evaluate("assert(nil)").unwrap_err().message,
+            "Assertion failed."
+        );
+        assert_eq!(evaluate("assert(nil, 42)").unwrap_err().message, "42");
+        assert_eq!(
+            evaluate("assert()").unwrap_err().message,
+            "Expected 1 to 2 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn typeof_names_each_kind_of_value() {
+        let source = "
+            class A {}
+            fun f() {}
+            var types = [
+                typeof(1), typeof(\"s\"), typeof(true), typeof(nil), typeof(f),
+                typeof(clock), typeof(fun () {}), typeof(A), typeof(A()),
+                typeof([]), typeof({})
+            ];
+            print types;";
+        assert_eq!(
+            output(source),
+            "[number, string, bool, nil, function, function, function, class, instance, array, map]\n"
+        );
+    }
+
+    #[test]
+    fn call_non_callable_errors() {
+        let error = run("\"nope\"();").unwrap_err();
+
+        assert_eq!(error.message, "Can only call functions and classes.");
+    }
+
+    #[test]
+    fn index_array_literal() {
+        assert_eq!(evaluate("[1, 2, 3][1]").unwrap(), Value::Int(2));
+        assert_eq!(evaluate("[[1], [2, 3]][1][0]").unwrap(), Value::Int(2));
+        assert_eq!(
+            output("var a = [1, \"b\", nil, []]; print a;"),
+            "[1, b, nil, []]\n"
+        );
+    }
+
+    #[test]
+    fn index_out_of_bounds_or_non_integer_errors() {
+        let error = evaluate("[1, 2, 3][3]").unwrap_err();
+        assert_eq!(error.message, "Array index out of bounds.");
+        assert_eq!(
+            evaluate("[1][-1]").unwrap_err().message,
+            "Array index out of bounds."
+        );
+
+        let error = evaluate("[1, 2][0.5]").unwrap_err();
+        assert_eq!(error.message, "Array index must be an integer.");
+        assert_eq!(
+            evaluate("[1][\"0\"]").unwrap_err().message,
+            "Array index must be an integer."
+        );
+        assert_eq!(
+            evaluate("1[0]").unwrap_err().message,
+            "Only arrays and maps can be indexed."
+        );
+    }
+
+    #[test]
+    fn map_literal_reads_keys() {
+        assert_eq!(
+            evaluate("{\"a\": 1, \"b\": 2}[\"b\"]").unwrap(),
+            Value::Int(2)
+        );
+        assert_eq!(
+            output("var m = {\"b\": [2], \"a\": 1}; print m; print {};"),
+            "{a: 1, b: [2]}\n{}\n"
+        );
+
+        let error = evaluate("{\"a\": 1}[\"c\"]").unwrap_err();
+        assert_eq!(error.message, "Undefined key 'c'.");
+    }
+
+    #[test]
+    fn index_assignment_updates_maps_and_arrays() {
+        let source = "
+            var m = {\"a\": 1};
+            m[\"b\"] = 2;
+            m[\"a\"] += 10;
+            var a = [1, 2];
+            a[1] = 3;
+            var r = m[\"a\"] + m[\"b\"] + a[1];";
+        assert_eq!(global(source, "r"), Value::Int(16));
+    }
+
+    #[test]
+    fn compound_index_assignment_evaluates_key_once() {
+        let source = "
+            var m = {\"a\": 1};
+            var calls = 0;
+            fun key() { calls = calls + 1; return \"a\"; }
+            m[key()] += 1;
+            var r = m[\"a\"];";
+        assert_eq!(global(source, "r"), Value::Int(2));
+        assert_eq!(global(source, "calls"), Value::Int(1));
+    }
+
+    #[test]
+    fn non_string_map_keys_error() {
+        assert_eq!(
+            evaluate("{1: 2}").unwrap_err().message,
+            "Map keys must be strings."
+        );
+        assert_eq!(
+            run("var m = {}; m[nil] = 1;").unwrap_err().message,
+            "Map keys must be strings."
+        );
+    }
+}
@@ -0,0 +1,17 @@
+pub mod ast_printer;
+pub mod callable;
+pub mod class;
+pub mod diagnostic;
+pub mod environment;
+pub mod function;
+pub mod interpreter;
+pub mod literal;
+pub mod native;
+pub mod parser;
+pub mod repl;
+pub mod resolver;
+pub mod rng;
+pub mod scanner;
+pub mod token;
+pub mod token_type;
+pub mod value;
@@ -0,0 +1,27 @@
+use std::fmt::{self, Display, Formatter};
+
+/// The value of a literal token, already converted from its source text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    /// A decimal literal with no fraction or exponent, or a hexadecimal,
+    /// binary, or octal one, that fits in an `i64`.
+    Int(i64),
+    Number(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+    Nil,
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Int(value) => write!(f, "{}", value),
+            Literal::Number(value) => write!(f, "{:?}", value),
+            Literal::Str(value) => write!(f, "{}", value),
+            Literal::Char(value) => write!(f, "{}", value),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::Nil => write!(f, "nil"),
+        }
+    }
+}
@@ -1,47 +1,202 @@
-use crate::scanner::Scanner;
+use rust_lox::ast_printer::AstPrinter;
+use rust_lox::diagnostic;
+use rust_lox::interpreter::{Interpreter, RuntimeError};
+use rust_lox::parser::Parser;
+use rust_lox::repl::{self, Command, Input, LineBuffer};
+use rust_lox::resolver::Resolver;
+use rust_lox::scanner::{DEFAULT_TAB_WIDTH, Scanner};
+use rust_lox::token::Token;
 
-mod scanner;
-mod token;
-mod token_type;
+/// The largest script `run_file` will read, which keeps an accidental
+/// binary or log file argument from exhausting memory.
+const MAX_SOURCE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The stack size of the thread that runs Lox, big enough for calls and
+/// expressions to nest to `DEFAULT_MAX_CALL_DEPTH` and
+/// `DEFAULT_MAX_NESTING_DEPTH` even in a debug build.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+const USAGE: &str = "Usage: rust-lox [--tokens [--debug] | --ast | --check] [script]";
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    if args.len() > 2 {
-        println!("Usage: rust-lox [script]");
+    let lox = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_main)
+        .expect("failed to spawn the interpreter thread");
+    if let Err(panic) = lox.join() {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+fn run_main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let Some(options) = parse_args(&args) else {
+        println!("{}", USAGE);
         std::process::exit(64);
-    } else if args.len() == 2 {
-        run_file(&args[1]);
-    } else {
-        run_prompt();
+    };
+
+    match &options.script {
+        Some(path) => run_file(path, options.mode),
+        None => run_prompt(options.mode),
+    }
+}
+
+/// What `run` does with the source it is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Scan, parse, resolve, and interpret.
+    Run,
+    /// Only scan, printing each token; `debug` prints them with `{:?}`
+    /// instead of their `Display` form.
+    Tokens { debug: bool },
+    /// Scan and parse, printing each statement's syntax tree.
+    Ast,
+    /// Scan, parse, and resolve, reporting any errors but running nothing.
+    Check,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    mode: Mode,
+    script: Option<String>,
+}
+
+/// Parses the command line, returning `None` for anything that should
+/// print the usage string instead.
+fn parse_args(args: &[String]) -> Option<Options> {
+    let mut tokens = false;
+    let mut debug = false;
+    let mut ast = false;
+    let mut check = false;
+    let mut script = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--tokens" | "-t" => tokens = true,
+            "--debug" => debug = true,
+            "--ast" => ast = true,
+            "--check" => check = true,
+            flag if flag.starts_with('-') && flag != "-" => return None,
+            path if script.is_none() => script = Some(path.to_string()),
+            _ => return None,
+        }
+    }
+
+    let mode = match (tokens, debug, ast, check) {
+        (false, false, false, false) => Mode::Run,
+        (false, false, true, false) => Mode::Ast,
+        (false, false, false, true) => Mode::Check,
+        (true, debug, false, false) => Mode::Tokens { debug },
+        // `--debug` only changes how tokens are printed, and only one kind
+        // of dump can be shown.
+        _ => return None,
+    };
+
+    Some(Options { mode, script })
+}
+
+/// Why `run` failed, which decides the process exit code.
+#[derive(Debug, PartialEq)]
+enum RunError {
+    /// A scan, parse, or resolution error; the program never started.
+    Compile,
+    Runtime,
+}
+
+impl RunError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Compile => 65,
+            RunError::Runtime => 70,
+        }
+    }
+}
+
+fn run_file(path: &str, mode: Mode) {
+    let source = match read_source(path, std::io::stdin(), MAX_SOURCE_BYTES) {
+        Ok(source) => source,
+        Err(error) => {
+            if error.kind() == std::io::ErrorKind::FileTooLarge {
+                eprintln!("{}", error);
+            } else {
+                eprintln!("Could not read {}: {}", path, error);
+            }
+            std::process::exit(read_error_exit_code(&error));
+        }
+    };
+    // Standard input has no name worth reporting.
+    let file_name = (path != "-").then_some(path);
+    if let Err(error) = run(&mut Interpreter::new(), &source, file_name, mode) {
+        std::process::exit(error.exit_code());
     }
 }
 
-fn run_file(path: &str) {
-    use std::fs;
-    let source = fs::read_to_string(path).expect("Could not read file");
-    run(&source);
+/// Reads the script at `path`, or all of `stdin` when `path` is `-`,
+/// failing with `FileTooLarge` rather than reading more than `max_bytes`.
+fn read_source(path: &str, stdin: impl std::io::Read, max_bytes: u64) -> std::io::Result<String> {
+    use std::io::{Error, ErrorKind, Read};
 
-    // if had_error {
-    //     std::process::exit(65);
-    // }
+    let reader: Box<dyn Read> = if path == "-" {
+        Box::new(stdin)
+    } else {
+        Box::new(std::fs::File::open(path)?)
+    };
+    let mut bytes = Vec::new();
+    reader.take(max_bytes + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(Error::new(
+            ErrorKind::FileTooLarge,
+            format!("Script is larger than the {} byte limit.", max_bytes),
+        ));
+    }
+
+    String::from_utf8(bytes).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+}
+
+/// The exit code for a script that `read_source` could not read: 64 for a
+/// script over the size limit, 65 for one that is not UTF-8, and 66 when
+/// the file itself cannot be read.
+fn read_error_exit_code(error: &std::io::Error) -> i32 {
+    match error.kind() {
+        std::io::ErrorKind::FileTooLarge => 64,
+        std::io::ErrorKind::InvalidData => 65,
+        _ => 66,
+    }
 }
 
-fn run_prompt() {
+fn run_prompt(mode: Mode) {
     use std::io::{self, Write};
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    let mut interpreter = Interpreter::new();
+    let mut buffer = LineBuffer::new();
 
     loop {
-        print!("> ");
+        print!("{}", buffer.prompt());
         stdout.flush().unwrap();
 
         let mut line = String::new();
         match stdin.read_line(&mut line) {
-            Ok(0) => break,
-            Ok(_) => {
-                run(&line);
-                // had_error = false;
+            Ok(0) => {
+                buffer.clear();
+                break;
+            }
+            Ok(_) if !buffer.is_pending() && line.trim_start().starts_with(':') => {
+                if let Some(command) = repl::meta_command(&line)
+                    && !run_command(&mut interpreter, command)
+                {
+                    break;
+                }
             }
+            Ok(_) => match buffer.push_line(&line) {
+                Input::Complete(source) => {
+                    // Errors were already reported; the next line starts afresh.
+                    if let Ok(Some(value)) = run_line(&mut interpreter, &source, mode) {
+                        println!("{}", value);
+                    }
+                }
+                Input::Incomplete | Input::Aborted => {}
+            },
             Err(error) => {
                 eprintln!("Error reading input: {}", error);
                 break;
@@ -50,20 +205,360 @@ fn run_prompt() {
     }
 }
 
-fn run(source: &str) {
+/// Carries out a REPL meta-command, returning whether the REPL should keep
+/// reading lines.
+fn run_command(interpreter: &mut Interpreter, command: Command) -> bool {
+    match command {
+        Command::Quit => return false,
+        Command::Help => println!("{}", repl::HELP),
+        Command::Tokens(source) => {
+            // Any scan errors were already reported.
+            let _ = run(interpreter, &source, None, Mode::Tokens { debug: false });
+        }
+        Command::Unknown(name) => {
+            eprintln!("Unknown command ':{}'. Type :help for a list.", name)
+        }
+    }
+    true
+}
+
+/// Runs one REPL entry. A bare expression is evaluated and its value
+/// returned for echoing; anything else runs like a script.
+fn run_line(
+    interpreter: &mut Interpreter,
+    source: &str,
+    mode: Mode,
+) -> Result<Option<String>, RunError> {
+    let expr = match mode {
+        Mode::Run => repl::bare_expression(source),
+        Mode::Tokens { .. } | Mode::Ast | Mode::Check => None,
+    };
+    let Some(expr) = expr else {
+        return run(interpreter, source, None, mode).map(|()| None);
+    };
+
+    if let Err(errors) = Resolver::new(interpreter).resolve_expr(&expr) {
+        for error in errors {
+            report(source, None, &error.token, &error.message);
+        }
+        return Err(RunError::Compile);
+    }
+
+    match interpreter.interpret_expression(&expr) {
+        Ok(value) => Ok(Some(value.to_display_string())),
+        Err(error) => {
+            runtime_error(&error);
+            Err(RunError::Runtime)
+        }
+    }
+}
+
+/// Runs `source`, reporting any errors to stderr as they are found.
+/// `file_name`, when given, prefixes each compile error.
+fn run(
+    interpreter: &mut Interpreter,
+    source: &str,
+    file_name: Option<&str>,
+    mode: Mode,
+) -> Result<(), RunError> {
     let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
+    if let Some(file_name) = file_name {
+        scanner = scanner.with_file_name(file_name);
+    }
+    scanner.scan_tokens();
+
+    // Warnings are shown too, but only errors stop the run.
+    for diagnostic in scanner.diagnostics() {
+        eprintln!("{}", diagnostic.render(file_name));
+        eprintln!(
+            "{}",
+            diagnostic::caret(
+                source,
+                diagnostic.line,
+                diagnostic.column,
+                DEFAULT_TAB_WIDTH
+            )
+        );
+    }
+    if scanner.had_error() {
+        return Err(RunError::Compile);
+    }
+
+    if let Mode::Tokens { debug } = mode {
+        for token in scanner.tokens() {
+            if debug {
+                println!("{:?}", token);
+            } else {
+                println!("{}", token);
+            }
+        }
+        return Ok(());
+    }
 
-    for token in tokens {
-        println!("{:?}", token);
+    let mut parser = Parser::new(scanner.tokens().to_vec());
+    let statements = match parser.parse() {
+        Ok(statements) => statements,
+        Err(errors) => {
+            for error in errors {
+                report(source, file_name, &error.token, &error.message);
+            }
+            return Err(RunError::Compile);
+        }
+    };
+
+    if mode == Mode::Ast {
+        for statement in &statements {
+            println!("{}", AstPrinter.print_stmt(statement));
+        }
+        return Ok(());
+    }
+
+    if let Err(errors) = Resolver::new(interpreter).resolve(&statements) {
+        for error in errors {
+            report(source, file_name, &error.token, &error.message);
+        }
+        return Err(RunError::Compile);
+    }
+    if mode == Mode::Check {
+        return Ok(());
     }
+
+    if let Err(error) = interpreter.interpret(&statements) {
+        runtime_error(&error);
+        return Err(RunError::Runtime);
+    }
+
+    Ok(())
 }
 
-fn error(line: usize, message: &str) {
-    report(line, "", message);
+/// Reports a compile error at `token`, followed by its line of `source`
+/// with a caret under it.
+fn report(source: &str, file_name: Option<&str>, token: &Token, message: &str) {
+    let (line, where_) = (token.line, token.location());
+    match file_name {
+        Some(file_name) => eprintln!("{}:{}: Error{}: {}", file_name, line, where_, message),
+        None => eprintln!("[line {}] Error{}: {}", line, where_, message),
+    }
+    eprintln!(
+        "{}",
+        diagnostic::caret(source, line, token.column, DEFAULT_TAB_WIDTH)
+    );
 }
 
-fn report(line: usize, where_: &str, message: &str) {
-    eprintln!("[line {}] Error{}: {}", line, where_, message);
-    // had_error = true;
+fn runtime_error(error: &RuntimeError) {
+    eprintln!("{}\n[line {}]", error.message, error.token.line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_source(source: &str) -> Result<(), RunError> {
+        run(&mut Interpreter::new(), source, None, Mode::Run)
+    }
+
+    #[test]
+    fn run_reports_success() {
+        assert_eq!(run_source("var a = 1; a = a + 1;"), Ok(()));
+        assert_eq!(run_source("var a = 1.;"), Ok(()));
+    }
+
+    #[test]
+    fn run_reports_compile_errors() {
+        assert_eq!(run_source("var a = @;"), Err(RunError::Compile));
+        assert_eq!(run_source("var a = ;"), Err(RunError::Compile));
+        assert_eq!(run_source("{ var a = a; }"), Err(RunError::Compile));
+        assert_eq!(RunError::Compile.exit_code(), 65);
+    }
+
+    #[test]
+    fn run_reports_runtime_errors() {
+        assert_eq!(run_source("var a = -nil;"), Err(RunError::Runtime));
+        assert_eq!(RunError::Runtime.exit_code(), 70);
+    }
+
+    #[test]
+    fn errors_do_not_poison_later_runs() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            run(&mut interpreter, "var a = 1; a = -nil;", None, Mode::Run),
+            Err(RunError::Runtime)
+        );
+        assert_eq!(run(&mut interpreter, "a = a + 1;", None, Mode::Run), Ok(()));
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_selects_mode_and_script() {
+        assert_eq!(
+            parse_args(&args(&["--tokens", "script.lox"])),
+            Some(Options {
+                mode: Mode::Tokens { debug: false },
+                script: Some("script.lox".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["-t", "--debug"])),
+            Some(Options {
+                mode: Mode::Tokens { debug: true },
+                script: None,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["--ast", "script.lox"])),
+            Some(Options {
+                mode: Mode::Ast,
+                script: Some("script.lox".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["--check", "script.lox"])),
+            Some(Options {
+                mode: Mode::Check,
+                script: Some("script.lox".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["script.lox"])),
+            Some(Options {
+                mode: Mode::Run,
+                script: Some("script.lox".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_args_rejects_invalid_combinations() {
+        assert_eq!(parse_args(&args(&["a.lox", "b.lox"])), None);
+        assert_eq!(parse_args(&args(&["--debug", "a.lox"])), None);
+        assert_eq!(parse_args(&args(&["--bogus"])), None);
+        assert_eq!(parse_args(&args(&["--ast", "--tokens"])), None);
+        assert_eq!(parse_args(&args(&["--ast", "--debug"])), None);
+        assert_eq!(parse_args(&args(&["--check", "--ast"])), None);
+    }
+
+    #[test]
+    fn read_source_dash_reads_stdin() {
+        let stdin = std::io::Cursor::new("print 1;");
+
+        assert_eq!(read_source("-", stdin, 64).unwrap(), "print 1;");
+    }
+
+    #[test]
+    fn read_source_opens_other_paths() {
+        let stdin = std::io::Cursor::new("print 1;");
+
+        assert!(read_source("no/such/script.lox", stdin, 64).is_err());
+        assert_eq!(
+            parse_args(&args(&["-"])).unwrap().script.as_deref(),
+            Some("-")
+        );
+    }
+
+    #[test]
+    fn read_source_rejects_oversized_scripts() {
+        let stdin = std::io::Cursor::new("print 12345;");
+        let error = read_source("-", stdin, 8).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::FileTooLarge);
+        assert_eq!(error.to_string(), "Script is larger than the 8 byte limit.");
+
+        let stdin = std::io::Cursor::new("print 1;");
+        assert_eq!(read_source("-", stdin, 8).unwrap(), "print 1;");
+    }
+
+    #[test]
+    fn read_errors_map_to_exit_codes() {
+        let stdin = std::io::Cursor::new(b"print \xff;".to_vec());
+        let error = read_source("-", stdin, 64).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(read_error_exit_code(&error), 65);
+
+        let stdin = std::io::Cursor::new("");
+        let error = read_source("no/such/script.lox", stdin, 64).unwrap_err();
+        assert_eq!(read_error_exit_code(&error), 66);
+
+        let stdin = std::io::Cursor::new("print 12345;");
+        let error = read_source("-", stdin, 8).unwrap_err();
+        assert_eq!(read_error_exit_code(&error), 64);
+    }
+
+    #[test]
+    fn run_line_echoes_bare_expressions_only() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            run_line(&mut interpreter, "1 + 2\n", Mode::Run),
+            Ok(Some("3".to_string()))
+        );
+        assert_eq!(
+            run_line(&mut interpreter, "var a = 1;\n", Mode::Run),
+            Ok(None)
+        );
+        assert_eq!(
+            run_line(&mut interpreter, "a\n", Mode::Run),
+            Ok(Some("1".to_string()))
+        );
+        assert_eq!(
+            run_line(&mut interpreter, "-nil\n", Mode::Run),
+            Err(RunError::Runtime)
+        );
+    }
+
+    #[test]
+    fn ast_mode_parses_without_executing() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(run(&mut interpreter, "-nil;", None, Mode::Ast), Ok(()));
+        assert_eq!(
+            run(&mut interpreter, "1 +;", None, Mode::Ast),
+            Err(RunError::Compile)
+        );
+    }
+
+    #[test]
+    fn check_mode_reports_errors_without_executing() {
+        assert_eq!(
+            run(&mut Interpreter::new(), "print -nil;", None, Mode::Check),
+            Ok(())
+        );
+        let error = run(&mut Interpreter::new(), "print (1;", None, Mode::Check).unwrap_err();
+        assert_eq!(error.exit_code(), 65);
+        assert_eq!(
+            run(&mut Interpreter::new(), "{ var a = a; }", None, Mode::Check),
+            Err(RunError::Compile)
+        );
+    }
+
+    #[test]
+    fn run_command_stops_only_on_quit() {
+        let mut interpreter = Interpreter::new();
+
+        assert!(!run_command(&mut interpreter, Command::Quit));
+        assert!(run_command(
+            &mut interpreter,
+            Command::Unknown("nope".to_string())
+        ));
+        assert!(run_command(
+            &mut interpreter,
+            Command::Tokens("-nil".to_string())
+        ));
+    }
+
+    #[test]
+    fn token_mode_does_not_execute() {
+        assert_eq!(
+            run(
+                &mut Interpreter::new(),
+                "-nil;",
+                None,
+                Mode::Tokens { debug: false }
+            ),
+            Ok(())
+        );
+    }
 }
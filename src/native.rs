@@ -0,0 +1,183 @@
+use crate::callable::Callable;
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::rng::Rng;
+use crate::token::Token;
+use crate::value::Value;
+
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The Rust side of a native function. An `Err` message is reported as a
+/// runtime error at the call site.
+pub type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value, String>;
+
+/// A function implemented in Rust and exposed to Lox code.
+pub struct NativeFunction {
+    name: &'static str,
+    arity: usize,
+    min_arity: usize,
+    function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: usize, function: NativeFn) -> Self {
+        NativeFunction {
+            name,
+            arity,
+            min_arity: arity,
+            function,
+        }
+    }
+
+    /// Makes the arguments after the first `min_arity` optional. The
+    /// function then receives only those actually passed.
+    pub fn with_min_arity(mut self, min_arity: usize) -> Self {
+        self.min_arity = min_arity;
+        self
+    }
+}
+
+impl Callable for NativeFunction {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn min_arity(&self) -> usize {
+        self.min_arity
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(interpreter, &arguments)
+            .map_err(|message| RuntimeError::new(paren, &message))
+    }
+}
+
+/// Defines every native function in `globals`.
+pub fn define_natives(globals: &mut Environment) {
+    let natives = [
+        NativeFunction::new("assert", 2, assert).with_min_arity(1),
+        NativeFunction::new("clock", 0, clock),
+        NativeFunction::new("len", 1, len),
+        NativeFunction::new("num", 1, num),
+        NativeFunction::new("random", 0, random),
+        NativeFunction::new("randomSeed", 1, random_seed),
+        NativeFunction::new("str", 1, str),
+        NativeFunction::new("substr", 3, substr),
+        NativeFunction::new("typeof", 1, type_of),
+    ];
+
+    for native in natives {
+        globals.define(native.name, Value::Callable(Rc::new(native)));
+    }
+}
+
+/// Fails with the optional message, stringified, unless the condition is
+/// truthy.
+fn assert(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    if arguments[0].is_truthy() {
+        return Ok(Value::Nil);
+    }
+
+    Err(match arguments.get(1) {
+        Some(message) => message.to_display_string(),
+        None => "Assertion failed.".to_string(),
+    })
+}
+
+/// Seconds since the Unix epoch.
+fn clock(_: &mut Interpreter, _: &[Value]) -> Result<Value, String> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|error| error.to_string())?;
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+/// The number of characters in a string.
+fn len(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string_argument("len", &arguments[0])?;
+    Ok(Value::Int(string.chars().count() as i64))
+}
+
+/// The characters of a string from `start` up to, but not including, `end`.
+fn substr(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string_argument("substr", &arguments[0])?;
+    let start = index_argument("substr", &arguments[1])?;
+    let end = index_argument("substr", &arguments[2])?;
+
+    let length = string.chars().count();
+    if start > end || end > length {
+        return Err(format!(
+            "Substring range {}..{} is out of bounds for a string of length {}.",
+            start, end, length
+        ));
+    }
+
+    Ok(Value::Str(
+        string.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+/// A pseudo-random number in `[0, 1)`.
+fn random(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(interpreter.rng().next_f64()))
+}
+
+/// Reseeds `random`, so that the numbers that follow repeat from run to run.
+fn random_seed(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let Some(seed) = arguments[0].as_i64() else {
+        return Err("randomSeed() expects an integer.".to_string());
+    };
+    *interpreter.rng() = Rng::new(seed as u64);
+    Ok(Value::Nil)
+}
+
+/// The value as `print` would show it.
+fn str(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(arguments[0].to_display_string()))
+}
+
+/// The name of the argument's type, such as `"number"` or `"instance"`.
+fn type_of(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(arguments[0].type_name().to_string()))
+}
+
+/// Parses a string as a number, ignoring surrounding whitespace. Text that
+/// isn't a finite number yields `nil` rather than an error, so scripts can
+/// test the result. Whole numbers that fit come back as integers.
+fn num(_: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string_argument("num", &arguments[0])?.trim();
+    if let Ok(number) = string.parse::<i64>() {
+        return Ok(Value::Int(number));
+    }
+    Ok(match string.parse::<f64>() {
+        Ok(number) if number.is_finite() => Value::Number(number),
+        _ => Value::Nil,
+    })
+}
+
+fn string_argument<'v>(name: &str, value: &'v Value) -> Result<&'v str, String> {
+    match value {
+        Value::Str(string) => Ok(string),
+        _ => Err(format!("{}() expects a string.", name)),
+    }
+}
+
+fn index_argument(name: &str, value: &Value) -> Result<usize, String> {
+    match value.as_i64().map(usize::try_from) {
+        Some(Ok(index)) => Ok(index),
+        _ => Err(format!(
+            "{}() expects indices to be non-negative whole numbers.",
+            name
+        )),
+    }
+}
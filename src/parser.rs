@@ -0,0 +1,1912 @@
+use crate::literal::Literal;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Identifies one variable, assignment, `this`, or `super` expression, so
+/// that later passes can attach facts to it, such as the scope distance the
+/// resolver finds. Ids are unique across every parse in the process, so
+/// expressions from different REPL lines never collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    fn next() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        ExprId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    /// `span` includes the parentheses.
+    Grouping {
+        expression: Box<Expr>,
+        span: Range<usize>,
+    },
+    Literal {
+        value: Literal,
+        span: Range<usize>,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Variable {
+        id: ExprId,
+        name: Token,
+    },
+    Assign {
+        id: ExprId,
+        name: Token,
+        value: Box<Expr>,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    /// `question` is the `?` token, used to report nesting too deep to
+    /// evaluate.
+    Ternary {
+        condition: Box<Expr>,
+        question: Token,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    /// `object.name = value`. For a compound assignment such as `+=`,
+    /// `operator` is the arithmetic operator that combines the current
+    /// value with `value`.
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        operator: Option<Token>,
+        value: Box<Expr>,
+    },
+    This {
+        id: ExprId,
+        keyword: Token,
+    },
+    Super {
+        id: ExprId,
+        keyword: Token,
+        method: Token,
+    },
+    /// An array literal, such as `[1, 2, 3]`.
+    Array {
+        elements: Vec<Expr>,
+        span: Range<usize>,
+    },
+    /// `bracket` is the closing `]`, used to report indexing errors.
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// `target[index] = value`, on an array or a map. `operator` is as for
+    /// `Set`.
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        operator: Option<Token>,
+        value: Box<Expr>,
+    },
+    /// A map literal, such as `{"a": 1}`. `brace` is the opening `{`, used to
+    /// report keys that are not strings.
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+        span: Range<usize>,
+    },
+    /// An anonymous function, such as `fun (a) { return a + 1; }`.
+    /// `keyword` is the `fun` token.
+    Function {
+        keyword: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+        span: Range<usize>,
+    },
+}
+
+impl Expr {
+    /// The byte range of the source this expression was parsed from. The
+    /// span runs from the first token of the expression to the last, so a
+    /// call's span includes its closing parenthesis.
+    pub fn span(&self) -> Range<usize> {
+        let start = |expr: &Expr| expr.span().start;
+        let end = |expr: &Expr| expr.span().end;
+        match self {
+            Expr::Binary { left, right, .. }
+            | Expr::Logical { left, right, .. }
+            | Expr::Set {
+                object: left,
+                value: right,
+                ..
+            }
+            | Expr::IndexSet {
+                object: left,
+                value: right,
+                ..
+            }
+            | Expr::Ternary {
+                condition: left,
+                else_expr: right,
+                ..
+            } => start(left)..end(right),
+            Expr::Unary { operator, right } => operator.span.start..end(right),
+            Expr::Assign { name, value, .. } => name.span.start..end(value),
+            Expr::Call { callee, paren, .. } => start(callee)..paren.span.end,
+            Expr::Get { object, name } => start(object)..name.span.end,
+            Expr::Index {
+                object, bracket, ..
+            } => start(object)..bracket.span.end,
+            Expr::Super {
+                keyword, method, ..
+            } => keyword.span.start..method.span.end,
+            Expr::Variable { name: token, .. } | Expr::This { keyword: token, .. } => {
+                token.span.clone()
+            }
+            Expr::Grouping { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Array { span, .. }
+            | Expr::Map { span, .. }
+            | Expr::Function { span, .. } => span.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    /// Like `Print`, but without the trailing newline.
+    Write(Expr),
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    Block(Vec<Stmt>),
+    /// `superclass` is always an `Expr::Variable`, and `methods` holds only
+    /// `Stmt::Function`s.
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Stmt>,
+    },
+    Function {
+        name: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+    },
+    /// `keyword` is the `if` token, used to report nesting too deep to
+    /// execute.
+    If {
+        keyword: Token,
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+    /// A variable that can't be assigned after its declaration.
+    Const {
+        name: Token,
+        initializer: Expr,
+    },
+    /// `keyword` is the `while` or `for` token, used to report running out
+    /// of execution budget.
+    While {
+        keyword: Token,
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    /// `foreach (name in collection) body`, over an array's elements or a
+    /// map's keys. `keyword` is the `foreach` token, used to report a
+    /// collection of the wrong type.
+    Foreach {
+        keyword: Token,
+        name: Token,
+        collection: Expr,
+        body: Box<Stmt>,
+    },
+    /// Runs the body of the first case whose value equals `discriminant`,
+    /// or `default` if none does. Cases don't fall through, and each body
+    /// is its own scope.
+    Switch {
+        discriminant: Expr,
+        cases: Vec<(Expr, Vec<Stmt>)>,
+        default: Option<Vec<Stmt>>,
+    },
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(token: &Token, message: &str) -> Self {
+        ParseError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Formats as `[line 3] Error at '}': Expect expression.`, or `Error at end`
+/// when the input ran out.
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error{}: {}",
+            self.token.line,
+            self.token.location(),
+            self.message
+        )
+    }
+}
+
+/// Desugars `name op= value` into `name = name op value`. A property or
+/// index target becomes a compound `Set` or `IndexSet` instead, so that its
+/// object and index are evaluated only once.
+fn compound_assignment(target: Expr, operator: &Token, value: Expr) -> Result<Expr, ParseError> {
+    let (token_type, lexeme) = match operator.token_type {
+        TokenType::PlusEqual => (TokenType::Plus, "+"),
+        TokenType::MinusEqual => (TokenType::Minus, "-"),
+        TokenType::StarEqual => (TokenType::Star, "*"),
+        TokenType::SlashEqual => (TokenType::Slash, "/"),
+        _ => (TokenType::Percent, "%"),
+    };
+    // The arithmetic operator reports errors at the compound operator.
+    let binary_operator = Token::new(
+        token_type,
+        lexeme.into(),
+        None,
+        operator.line,
+        operator.column,
+        operator.span.start..operator.span.start + 1,
+    );
+
+    match target {
+        Expr::Variable { id, name } => Ok(Expr::Assign {
+            id: ExprId::next(),
+            name: name.clone(),
+            value: Box::new(Expr::Binary {
+                left: Box::new(Expr::Variable { id, name }),
+                operator: binary_operator,
+                right: Box::new(value),
+            }),
+        }),
+        Expr::Get { object, name } => Ok(Expr::Set {
+            object,
+            name,
+            operator: Some(binary_operator),
+            value: Box::new(value),
+        }),
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => Ok(Expr::IndexSet {
+            object,
+            bracket,
+            index,
+            operator: Some(binary_operator),
+            value: Box::new(value),
+        }),
+        _ => Err(ParseError::new(operator, "Invalid assignment target.")),
+    }
+}
+
+/// The most arguments a call, or parameters a function, may have.
+const MAX_ARGUMENTS: usize = 255;
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    /// How many function bodies enclose the current token, so `return` can
+    /// be rejected outside of them.
+    function_depth: usize,
+}
+
+impl Parser {
+    /// Creates a parser over `tokens`, which must end with an `Eof` token.
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            current: 0,
+            function_depth: 0,
+        }
+    }
+
+    /// Parses a whole program, recovering after each error so that every
+    /// syntax error in the input is reported.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses the whole input as a single expression.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            return Err(ParseError::new(self.peek(), "Expect end of expression."));
+        }
+
+        Ok(expr)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_types(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+        // `fun` without a name starts an anonymous function expression.
+        if self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier) {
+            self.advance();
+            return self.function("function");
+        }
+        if self.match_types(&[TokenType::Var]) {
+            return self.var_declaration();
+        }
+        if self.match_types(&[TokenType::Const]) {
+            return self.const_declaration();
+        }
+
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .clone();
+
+        let superclass = if self.match_types(&[TokenType::Less]) {
+            let name = self
+                .consume(TokenType::Identifier, "Expect superclass name.")?
+                .clone();
+            Some(Expr::Variable {
+                id: ExprId::next(),
+                name,
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, &format!("Expect {} name.", kind))?
+            .clone();
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+        let (params, body) = self.function_rest(kind)?;
+
+        Ok(Stmt::Function { name, params, body })
+    }
+
+    /// Parses a function's parameters and body, after its opening `(`.
+    fn function_rest(&mut self, kind: &str) -> Result<(Vec<Token>, Rc<Vec<Stmt>>), ParseError> {
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    return Err(ParseError::new(
+                        self.peek(),
+                        "Can't have more than 255 parameters.",
+                    ));
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.")?
+                        .clone(),
+                );
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        let body = Rc::new(body?);
+
+        Ok((params, body))
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .clone();
+
+        let initializer = if self.match_types(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Stmt::Var { name, initializer })
+    }
+
+    fn const_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect constant name.")?
+            .clone();
+        self.consume(TokenType::Equal, "Expect '=' after constant name.")?;
+        let initializer = self.expression()?;
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after constant declaration.",
+        )?;
+        Ok(Stmt::Const { name, initializer })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_types(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_types(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_types(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_types(&[TokenType::Write]) {
+            return self.write_statement();
+        }
+        if self.match_types(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_types(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_types(&[TokenType::Foreach]) {
+            return self.foreach_statement();
+        }
+        if self.match_types(&[TokenType::Switch]) {
+            return self.switch_statement();
+        }
+        if self.match_types(&[TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(self.block()?));
+        }
+
+        self.expression_statement()
+    }
+
+    /// Parses a `for` loop, desugaring it into a `while` loop wrapped in a
+    /// block that scopes the initializer.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_types(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_types(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(&TokenType::Semicolon) {
+            let start = self.peek().span.start;
+            Expr::Literal {
+                value: Literal::Bool(true),
+                span: start..start,
+            }
+        } else {
+            self.expression()?
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+        body = Stmt::While {
+            keyword,
+            condition,
+            body: Box::new(body),
+        };
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        // An `else` binds to the nearest preceding `if`.
+        let else_branch = if self.match_types(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            keyword,
+            condition,
+            body,
+        })
+    }
+
+    fn foreach_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'foreach'.")?;
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .clone();
+        self.consume(TokenType::In, "Expect 'in' after foreach variable.")?;
+        let collection = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after foreach collection.",
+        )?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::Foreach {
+            keyword,
+            name,
+            collection,
+            body,
+        })
+    }
+
+    fn switch_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'switch'.")?;
+        let discriminant = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after switch value.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before switch body.")?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.match_types(&[TokenType::Case]) {
+                let value = self.expression()?;
+                self.consume(TokenType::Colon, "Expect ':' after case value.")?;
+                cases.push((value, self.case_body()?));
+            } else if self.match_types(&[TokenType::Default]) {
+                if default.is_some() {
+                    return Err(ParseError::new(
+                        self.previous(),
+                        "Already a default case in this switch.",
+                    ));
+                }
+                self.consume(TokenType::Colon, "Expect ':' after 'default'.")?;
+                default = Some(self.case_body()?);
+            } else {
+                return Err(ParseError::new(
+                    self.peek(),
+                    "Expect 'case' or 'default' in switch body.",
+                ));
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after switch body.")?;
+        Ok(Stmt::Switch {
+            discriminant,
+            cases,
+            default,
+        })
+    }
+
+    /// Parses the statements of one case, up to the next case, default, or
+    /// the end of the switch.
+    fn case_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+        while !self.check(&TokenType::Case)
+            && !self.check(&TokenType::Default)
+            && !self.check(&TokenType::RightBrace)
+            && !self.is_at_end()
+        {
+            statements.push(self.declaration()?);
+        }
+
+        Ok(statements)
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(value))
+    }
+
+    fn write_statement(&mut self) -> Result<Stmt, ParseError> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Write(value))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        if self.function_depth == 0 {
+            return Err(ParseError::new(
+                &keyword,
+                "Can't return from top-level code.",
+            ));
+        }
+
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.ternary()?;
+
+        if self.match_types(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            return match expr {
+                Expr::Variable { name, .. } => Ok(Expr::Assign {
+                    id: ExprId::next(),
+                    name,
+                    value: Box::new(value),
+                }),
+                Expr::Get { object, name } => Ok(Expr::Set {
+                    object,
+                    name,
+                    operator: None,
+                    value: Box::new(value),
+                }),
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => Ok(Expr::IndexSet {
+                    object,
+                    bracket,
+                    index,
+                    operator: None,
+                    value: Box::new(value),
+                }),
+                _ => Err(ParseError::new(&equals, "Invalid assignment target.")),
+            };
+        }
+
+        if self.match_types(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+            TokenType::PercentEqual,
+        ]) {
+            let operator = self.previous().clone();
+            let value = self.assignment()?;
+            return compound_assignment(expr, &operator, value);
+        }
+
+        Ok(expr)
+    }
+
+    fn ternary(&mut self) -> Result<Expr, ParseError> {
+        let condition = self.or()?;
+
+        if self.match_types(&[TokenType::Question]) {
+            let question = self.previous().clone();
+            let then_expr = self.expression()?;
+            self.consume(
+                TokenType::Colon,
+                "Expect ':' after then branch of conditional expression.",
+            )?;
+            // Recursing makes `a ? b : c ? d : e` group to the right.
+            let else_expr = self.ternary()?;
+
+            return Ok(Expr::Ternary {
+                condition: Box::new(condition),
+                question,
+                then_expr: Box::new(then_expr),
+                else_expr: Box::new(else_expr),
+            });
+        }
+
+        Ok(condition)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+
+        while self.match_types(&[TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_or()?;
+
+        while self.match_types(&[TokenType::And]) {
+            let operator = self.previous().clone();
+            let right = self.bit_or()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // The bitwise operators follow C's precedence: `&`, `^`, and `|` bind
+    // more loosely than equality, and shifts more tightly than comparison.
+    fn bit_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.match_types(&[TokenType::Pipe]) {
+            let operator = self.previous().clone();
+            let right = self.bit_xor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_and()?;
+
+        while self.match_types(&[TokenType::Caret]) {
+            let operator = self.previous().clone();
+            let right = self.bit_and()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality()?;
+
+        while self.match_types(&[TokenType::Ampersand]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.comparison()?;
+
+        while self.match_types(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            let operator = self.previous().clone();
+            let right = self.comparison()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.shift()?;
+
+        while self.match_types(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
+
+        while self.match_types(&[TokenType::LessLess, TokenType::GreaterGreater]) {
+            let operator = self.previous().clone();
+            let right = self.term()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.match_types(&[TokenType::Minus, TokenType::Plus]) {
+            let operator = self.previous().clone();
+            let right = self.factor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.match_types(&[TokenType::Slash, TokenType::Star, TokenType::Percent]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_types(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Unary {
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        self.power()
+    }
+
+    /// Parses `**`, which is right-associative and binds tighter than a
+    /// unary operator on its left, so `-2 ** 2` is `-4`, while its right
+    /// operand may itself be negated, as in `2 ** -1`.
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.call()?;
+
+        if self.match_types(&[TokenType::StarStar]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_types(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_types(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(TokenType::RightBracket, "Expect ']' after index.")?
+                    .clone();
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+            } else if self.match_types(&[TokenType::Dot]) {
+                let name = self
+                    .consume(TokenType::Identifier, "Expect property name after '.'.")?
+                    .clone();
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    return Err(ParseError::new(
+                        self.peek(),
+                        "Can't have more than 255 arguments.",
+                    ));
+                }
+                arguments.push(self.expression()?);
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+
+        Ok(Expr::Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+
+        if self.match_types(&[TokenType::False, TokenType::True, TokenType::Nil]) {
+            let value = match self.previous().token_type {
+                TokenType::False => Literal::Bool(false),
+                TokenType::True => Literal::Bool(true),
+                _ => Literal::Nil,
+            };
+            return Ok(Expr::Literal {
+                value,
+                span: self.previous().span.clone(),
+            });
+        }
+
+        if self.match_types(&[TokenType::Number, TokenType::String, TokenType::Char]) {
+            let token = self.previous();
+            return Ok(Expr::Literal {
+                value: token.literal.clone().unwrap_or(Literal::Nil),
+                span: token.span.clone(),
+            });
+        }
+
+        if self.match_types(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self
+                .consume(TokenType::Identifier, "Expect superclass method name.")?
+                .clone();
+            return Ok(Expr::Super {
+                id: ExprId::next(),
+                keyword,
+                method,
+            });
+        }
+
+        if self.match_types(&[TokenType::Fun]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+            let (params, body) = self.function_rest("function")?;
+            return Ok(Expr::Function {
+                keyword,
+                params,
+                body,
+                span: start..self.previous().span.end,
+            });
+        }
+
+        if self.match_types(&[TokenType::This]) {
+            return Ok(Expr::This {
+                id: ExprId::next(),
+                keyword: self.previous().clone(),
+            });
+        }
+
+        if self.match_types(&[TokenType::Identifier]) {
+            return Ok(Expr::Variable {
+                id: ExprId::next(),
+                name: self.previous().clone(),
+            });
+        }
+
+        if self.match_types(&[TokenType::LeftBracket]) {
+            let mut elements = Vec::new();
+            if !self.check(&TokenType::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.match_types(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            let bracket =
+                self.consume(TokenType::RightBracket, "Expect ']' after array elements.")?;
+            return Ok(Expr::Array {
+                elements,
+                span: start..bracket.span.end,
+            });
+        }
+
+        // A `{` only reaches here in expression position; statements treat it
+        // as the start of a block.
+        if self.match_types(&[TokenType::LeftBrace]) {
+            let brace = self.previous().clone();
+            let mut entries = Vec::new();
+            if !self.check(&TokenType::RightBrace) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                    entries.push((key, self.expression()?));
+                    if !self.match_types(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            let end = self
+                .consume(TokenType::RightBrace, "Expect '}' after map entries.")?
+                .span
+                .end;
+            return Ok(Expr::Map {
+                brace,
+                entries,
+                span: start..end,
+            });
+        }
+
+        if self.match_types(&[TokenType::LeftParen]) {
+            let expression = Box::new(self.expression()?);
+            let paren = self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping {
+                expression,
+                span: start..paren.span.end,
+            });
+        }
+
+        Err(ParseError::new(self.peek(), "Expect expression."))
+    }
+
+    /// Discards tokens until the start of the next statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::Const
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Foreach
+                | TokenType::Switch
+                | TokenType::Print
+                | TokenType::Write
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    /// Consumes the next token if it has one of `types`.
+    pub(crate) fn match_types(&mut self, types: &[TokenType]) -> bool {
+        for token_type in types {
+            if self.check(token_type) {
+                self.advance();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Consumes the next token if it has `token_type`, and otherwise fails
+    /// with `message` at that token.
+    pub(crate) fn consume(
+        &mut self,
+        token_type: TokenType,
+        message: &str,
+    ) -> Result<&Token, ParseError> {
+        if self.check(&token_type) {
+            return Ok(self.advance());
+        }
+
+        Err(ParseError::new(self.peek(), message))
+    }
+
+    /// Whether the next token has `token_type`, without consuming it.
+    pub(crate) fn check(&self, token_type: &TokenType) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+        &self.peek().token_type == token_type
+    }
+
+    /// Consumes and returns the next token. At the end of input this keeps
+    /// returning the last token consumed.
+    pub(crate) fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    /// Whether the token after the next one has `token_type`.
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| &token.token_type == token_type)
+    }
+
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    /// The next token, not yet consumed.
+    pub(crate) fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    /// The token most recently consumed. Only valid after a token has been
+    /// consumed.
+    pub(crate) fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parser(source: &str) -> Parser {
+        let mut scanner = Scanner::new(source);
+        Parser::new(scanner.scan_tokens().clone())
+    }
+
+    fn parse(source: &str) -> Result<Expr, ParseError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        Parser::new(tokens).parse_expression()
+    }
+
+    fn assert_number(expr: &Expr, expected: f64) {
+        match expr {
+            Expr::Literal {
+                value: Literal::Int(value),
+                ..
+            } => assert_eq!(*value as f64, expected),
+            Expr::Literal {
+                value: Literal::Number(value),
+                ..
+            } => assert_eq!(*value, expected),
+            _ => panic!("expected number literal, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn parse_respects_precedence() {
+        let expr = parse("1 + 2 * 3").unwrap();
+
+        let Expr::Binary {
+            left,
+            operator,
+            right,
+        } = expr
+        else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(operator.token_type, TokenType::Plus);
+        assert_number(&left, 1.0);
+
+        let Expr::Binary {
+            left,
+            operator,
+            right,
+        } = *right
+        else {
+            panic!("expected nested binary expression");
+        };
+        assert_eq!(operator.token_type, TokenType::Star);
+        assert_number(&left, 2.0);
+        assert_number(&right, 3.0);
+    }
+
+    #[test]
+    fn parse_is_left_associative() {
+        let expr = parse("1 - 2 - 3").unwrap();
+
+        let Expr::Binary { left, right, .. } = expr else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(*left, Expr::Binary { .. }));
+        assert_number(&right, 3.0);
+    }
+
+    #[test]
+    fn expression_spans_cover_their_source() {
+        let expr = parse("1 + 2").unwrap();
+        assert_eq!(expr.span(), 0..5);
+        let Expr::Binary { left, right, .. } = &expr else {
+            panic!("expected binary");
+        };
+        assert_eq!((left.span(), right.span()), (0..1, 4..5));
+
+        let source_span = |source: &str| {
+            let expr = parse(source).unwrap();
+            source[expr.span()].to_string()
+        };
+        assert_eq!(source_span("f(g(1), [2])  "), "f(g(1), [2])");
+        assert_eq!(source_span(" -a.b[0] ? {} : (nil)"), "-a.b[0] ? {} : (nil)");
+        assert_eq!(
+            source_span("fun (a) { return a; }"),
+            "fun (a) { return a; }"
+        );
+
+        let Expr::Call { arguments, .. } = parse("f(g(1), 2)").unwrap() else {
+            panic!("expected call");
+        };
+        assert_eq!(arguments[0].span(), 2..6);
+    }
+
+    #[test]
+    fn parse_grouping() {
+        let expr = parse("(1 + 2)").unwrap();
+
+        let Expr::Grouping { expression, span } = expr else {
+            panic!("expected grouping");
+        };
+        assert!(matches!(*expression, Expr::Binary { .. }));
+        assert_eq!(span, 0..7);
+    }
+
+    #[test]
+    fn parse_and_binds_tighter_than_or() {
+        let expr = parse("a or b and c").unwrap();
+
+        let Expr::Logical {
+            operator, right, ..
+        } = expr
+        else {
+            panic!("expected logical expression");
+        };
+        assert_eq!(operator.token_type, TokenType::Or);
+        assert!(matches!(
+            *right,
+            Expr::Logical { ref operator, .. } if operator.token_type == TokenType::And
+        ));
+    }
+
+    #[test]
+    fn parse_unary_and_literals() {
+        let expr = parse("!-true").unwrap();
+
+        let Expr::Unary { operator, right } = expr else {
+            panic!("expected unary expression");
+        };
+        assert_eq!(operator.token_type, TokenType::Bang);
+        let Expr::Unary { right, .. } = *right else {
+            panic!("expected nested unary expression");
+        };
+        assert!(matches!(
+            *right,
+            Expr::Literal {
+                value: Literal::Bool(true),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_reports_missing_expression() {
+        let error = parse("(1 +").unwrap_err();
+
+        assert_eq!(error.message, "Expect expression.");
+        assert_eq!(error.token.token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn parse_reports_unclosed_grouping() {
+        let error = parse("(1 + 2").unwrap_err();
+
+        assert_eq!(error.message, "Expect ')' after expression.");
+    }
+
+    #[test]
+    fn parse_reports_every_broken_statement() {
+        let mut scanner = Scanner::new("1 + ;\n2;\n(3 * 4;\n5;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "Expect expression.");
+        assert_eq!(errors[0].token.line, 1);
+        assert_eq!(errors[1].message, "Expect ')' after expression.");
+        assert_eq!(errors[1].token.line, 3);
+    }
+
+    fn parse_program(source: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn parse_collects_expression_statements() {
+        let statements = parse_program("1; 2 + 3;");
+
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(statements[0], Stmt::Expression(_)));
+    }
+
+    #[test]
+    fn parse_print_statement() {
+        let statements = parse_program("print 1 + 2;");
+
+        assert_eq!(statements.len(), 1);
+        let Stmt::Print(Expr::Binary { operator, .. }) = &statements[0] else {
+            panic!("expected print statement");
+        };
+        assert_eq!(operator.token_type, TokenType::Plus);
+    }
+
+    #[test]
+    fn parse_var_declaration() {
+        let statements = parse_program("var a = 1; var b; print a;");
+
+        let Stmt::Var { name, initializer } = &statements[0] else {
+            panic!("expected var declaration");
+        };
+        assert_eq!(&*name.lexeme, "a");
+        assert!(matches!(initializer, Some(Expr::Literal { .. })));
+        assert!(matches!(
+            &statements[1],
+            Stmt::Var {
+                initializer: None,
+                ..
+            }
+        ));
+        assert!(matches!(&statements[2], Stmt::Print(Expr::Variable { .. })));
+    }
+
+    #[test]
+    fn parse_const_requires_initializer() {
+        let statements = parse_program("const a = 1;");
+        assert!(matches!(&statements[0], Stmt::Const { name, .. } if &*name.lexeme == "a"));
+
+        let mut scanner = Scanner::new("const a;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+        assert_eq!(errors[0].message, "Expect '=' after constant name.");
+    }
+
+    #[test]
+    fn parse_switch_collects_cases_and_default() {
+        let statements = parse_program("switch (x) { case 1: case 2: print 2; default: print 3; }");
+
+        let Stmt::Switch { cases, default, .. } = &statements[0] else {
+            panic!("expected switch");
+        };
+        assert_eq!(cases.len(), 2);
+        assert!(cases[0].1.is_empty());
+        assert_eq!(cases[1].1.len(), 1);
+        assert_eq!(default.as_ref().map(Vec::len), Some(1));
+
+        let mut scanner = Scanner::new("switch (x) { default: default: }");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+        assert_eq!(errors[0].message, "Already a default case in this switch.");
+    }
+
+    #[test]
+    fn parse_assignment_is_right_associative() {
+        let expr = parse("a = b = 3").unwrap();
+
+        let Expr::Assign { name, value, .. } = expr else {
+            panic!("expected assignment");
+        };
+        assert_eq!(&*name.lexeme, "a");
+        let Expr::Assign { name, value, .. } = *value else {
+            panic!("expected nested assignment");
+        };
+        assert_eq!(&*name.lexeme, "b");
+        assert_number(&value, 3.0);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_assignment_target() {
+        let error = parse("1 = 2").unwrap_err();
+
+        assert_eq!(error.message, "Invalid assignment target.");
+        assert_eq!(error.token.token_type, TokenType::Equal);
+
+        let error = parse("a + b = 2").unwrap_err();
+        assert_eq!(error.message, "Invalid assignment target.");
+    }
+
+    #[test]
+    fn parse_nested_blocks() {
+        let statements = parse_program("{ var a = 1; { print a; } }");
+
+        let Stmt::Block(outer) = &statements[0] else {
+            panic!("expected block");
+        };
+        assert_eq!(outer.len(), 2);
+        assert!(matches!(&outer[1], Stmt::Block(inner) if inner.len() == 1));
+    }
+
+    #[test]
+    fn parse_unclosed_block_errors() {
+        let mut scanner = Scanner::new("{ print 1;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect '}' after block.");
+    }
+
+    #[test]
+    fn parse_dangling_else_binds_to_nearest_if() {
+        let statements = parse_program("if (a) if (b) print 1; else print 2;");
+
+        let Stmt::If {
+            then_branch,
+            else_branch,
+            ..
+        } = &statements[0]
+        else {
+            panic!("expected if statement");
+        };
+        assert!(else_branch.is_none());
+        assert!(matches!(
+            then_branch.as_ref(),
+            Stmt::If {
+                else_branch: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_if_requires_parentheses() {
+        let mut scanner = Scanner::new("if true print 1;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect '(' after 'if'.");
+
+        let mut scanner = Scanner::new("if (true print 1;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect ')' after if condition.");
+    }
+
+    #[test]
+    fn parse_fun_expression_and_declaration() {
+        let statements = parse_program("fun f() {} var g = fun (a, b) { return a; }; fun () {};");
+
+        assert!(matches!(&statements[0], Stmt::Function { name, .. } if &*name.lexeme == "f"));
+        let Stmt::Var {
+            initializer: Some(Expr::Function { params, body, .. }),
+            ..
+        } = &statements[1]
+        else {
+            panic!("expected a function expression initializer");
+        };
+        assert_eq!(params.len(), 2);
+        assert!(matches!(body[0], Stmt::Return { .. }));
+        assert!(matches!(
+            &statements[2],
+            Stmt::Expression(Expr::Function { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_for_desugars_into_while() {
+        let statements = parse_program("for (var i = 0; i < 3; i = i + 1) print i;");
+
+        let Stmt::Block(outer) = &statements[0] else {
+            panic!("expected block around the initializer");
+        };
+        assert!(matches!(&outer[0], Stmt::Var { .. }));
+        let Stmt::While { body, .. } = &outer[1] else {
+            panic!("expected while loop");
+        };
+        let Stmt::Block(body) = body.as_ref() else {
+            panic!("expected block around the body and increment");
+        };
+        assert!(matches!(&body[0], Stmt::Print(_)));
+        assert!(matches!(&body[1], Stmt::Expression(Expr::Assign { .. })));
+    }
+
+    #[test]
+    fn parse_for_with_omitted_clauses() {
+        let statements = parse_program("for (;;) print 1;");
+
+        let Stmt::While {
+            condition, body, ..
+        } = &statements[0]
+        else {
+            panic!("expected a bare while loop");
+        };
+        assert!(matches!(
+            condition,
+            Expr::Literal {
+                value: Literal::Bool(true),
+                ..
+            }
+        ));
+        assert!(matches!(body.as_ref(), Stmt::Print(_)));
+    }
+
+    #[test]
+    fn parse_function_declaration() {
+        let statements = parse_program("fun add(a, b) { print a + b; }");
+
+        let Stmt::Function { name, params, body } = &statements[0] else {
+            panic!("expected function declaration");
+        };
+        assert_eq!(&*name.lexeme, "add");
+        let params: Vec<&str> = params.iter().map(|param| &*param.lexeme).collect();
+        assert_eq!(params, ["a", "b"]);
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn parse_chained_calls() {
+        let expr = parse("f(1)(2, 3)").unwrap();
+
+        let Expr::Call {
+            callee, arguments, ..
+        } = expr
+        else {
+            panic!("expected call");
+        };
+        assert_eq!(arguments.len(), 2);
+        assert!(matches!(*callee, Expr::Call { ref arguments, .. } if arguments.len() == 1));
+    }
+
+    #[test]
+    fn parse_rejects_too_many_arguments() {
+        let arguments = vec!["1"; 256].join(", ");
+        let error = parse(&format!("f({})", arguments)).unwrap_err();
+
+        assert_eq!(error.message, "Can't have more than 255 arguments.");
+
+        let params: Vec<String> = (0..256).map(|i| format!("p{}", i)).collect();
+        let source = format!("fun f({}) {{}}", params.join(", "));
+        let mut scanner = Scanner::new(&source);
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+        assert_eq!(errors[0].message, "Can't have more than 255 parameters.");
+    }
+
+    #[test]
+    fn parse_return_statement() {
+        let statements = parse_program("fun f() { return 1; return; }");
+
+        let Stmt::Function { body, .. } = &statements[0] else {
+            panic!("expected function declaration");
+        };
+        assert!(matches!(body[0], Stmt::Return { value: Some(_), .. }));
+        assert!(matches!(body[1], Stmt::Return { value: None, .. }));
+    }
+
+    #[test]
+    fn parse_rejects_top_level_return() {
+        let mut scanner = Scanner::new("return 1;");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Can't return from top-level code.");
+    }
+
+    #[test]
+    fn parse_class_declaration() {
+        let statements = parse_program("class Pair { first() {} second(a) {} }");
+
+        let Stmt::Class {
+            name,
+            superclass,
+            methods,
+        } = &statements[0]
+        else {
+            panic!("expected class declaration");
+        };
+        assert_eq!(&*name.lexeme, "Pair");
+        assert!(superclass.is_none());
+        assert_eq!(methods.len(), 2);
+        assert!(matches!(&methods[1], Stmt::Function { params, .. } if params.len() == 1));
+    }
+
+    #[test]
+    fn parse_subclass_and_super_call() {
+        let statements = parse_program("class B < A { f() { super.f(); } }");
+
+        let Stmt::Class {
+            superclass: Some(Expr::Variable { name, .. }),
+            methods,
+            ..
+        } = &statements[0]
+        else {
+            panic!("expected subclass declaration");
+        };
+        assert_eq!(&*name.lexeme, "A");
+        let Stmt::Function { body, .. } = &methods[0] else {
+            panic!("expected method");
+        };
+        assert!(matches!(
+            &body[0],
+            Stmt::Expression(Expr::Call { callee, .. })
+                if matches!(callee.as_ref(), Expr::Super { method, .. } if &*method.lexeme == "f")
+        ));
+    }
+
+    #[test]
+    fn parse_property_get_and_set() {
+        let expr = parse("a.b.c = 1").unwrap();
+
+        let Expr::Set { object, name, .. } = expr else {
+            panic!("expected property assignment");
+        };
+        assert_eq!(&*name.lexeme, "c");
+        assert!(matches!(*object, Expr::Get { ref name, .. } if &*name.lexeme == "b"));
+    }
+
+    #[test]
+    fn parse_print_requires_semicolon() {
+        let mut scanner = Scanner::new("print 1");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect ';' after value.");
+    }
+
+    #[test]
+    fn parse_ternary_is_right_associative() {
+        let expr = parse("a ? 1 : b ? 2 : 3").unwrap();
+
+        let Expr::Ternary {
+            then_expr,
+            else_expr,
+            ..
+        } = expr
+        else {
+            panic!("expected conditional expression");
+        };
+        assert_number(&then_expr, 1.0);
+        assert!(matches!(*else_expr, Expr::Ternary { .. }));
+
+        let error = parse("a ? 1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expect ':' after then branch of conditional expression."
+        );
+    }
+
+    #[test]
+    fn parse_compound_assignment_desugars() {
+        let expr = parse("a -= 2").unwrap();
+
+        let Expr::Assign { name, value, .. } = expr else {
+            panic!("expected assignment");
+        };
+        assert_eq!(&*name.lexeme, "a");
+        let Expr::Binary {
+            left,
+            operator,
+            right,
+        } = *value
+        else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(*left, Expr::Variable { ref name, .. } if &*name.lexeme == "a"));
+        assert_eq!(operator.token_type, TokenType::Minus);
+        assert_number(&right, 2.0);
+
+        let Expr::Set {
+            object,
+            operator: Some(operator),
+            value,
+            ..
+        } = parse("a.b *= 3").unwrap()
+        else {
+            panic!("expected compound property assignment");
+        };
+        assert!(matches!(*object, Expr::Variable { ref name, .. } if &*name.lexeme == "a"));
+        assert_eq!(operator.token_type, TokenType::Star);
+        assert_number(&value, 3.0);
+
+        let error = parse("1 += 2").unwrap_err();
+        assert_eq!(error.message, "Invalid assignment target.");
+    }
+
+    #[test]
+    fn parse_error_display_names_location() {
+        let mut scanner = Scanner::new("print 1;\n\n}");
+        let tokens = scanner.scan_tokens().clone();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 3] Error at '}': Expect expression."
+        );
+
+        let error = parse("\n(1 +").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 2] Error at end: Expect expression."
+        );
+    }
+
+    #[test]
+    fn match_types_consumes_only_on_hit() {
+        let mut parser = parser("var x");
+
+        assert!(!parser.match_types(&[TokenType::Fun, TokenType::Class]));
+        assert_eq!(parser.peek().token_type, TokenType::Var);
+
+        assert!(parser.match_types(&[TokenType::Fun, TokenType::Var]));
+        assert_eq!(parser.previous().token_type, TokenType::Var);
+        assert!(parser.check(&TokenType::Identifier));
+    }
+
+    #[test]
+    fn consume_errors_with_message() {
+        let mut parser = parser("x");
+
+        let error = parser
+            .consume(TokenType::Semicolon, "Expect ';'.")
+            .unwrap_err();
+        assert_eq!(error.message, "Expect ';'.");
+        assert_eq!(&*error.token.lexeme, "x");
+
+        let token = parser
+            .consume(TokenType::Identifier, "Expect name.")
+            .unwrap();
+        assert_eq!(&*token.lexeme, "x");
+        assert!(parser.is_at_end());
+        assert!(!parser.check(&TokenType::Eof));
+        assert_eq!(&*parser.advance().lexeme, "x");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::parser::{Expr, Parser};
+use crate::scanner::{Scanner, Unterminated};
+
+/// What the REPL should do after reading a line.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// The buffered lines form complete input, ready to run.
+    Complete(String),
+    /// More lines are needed before the input can run.
+    Incomplete,
+    /// The user gave up on the pending input with an empty line.
+    Aborted,
+}
+
+/// Collects REPL lines until they form input that can run on its own, so
+/// that a block or function can be typed across several lines.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        LineBuffer::default()
+    }
+
+    /// Whether earlier lines are waiting for a continuation.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// The prompt to show before reading the next line.
+    pub fn prompt(&self) -> &'static str {
+        if self.is_pending() { "... " } else { "> " }
+    }
+
+    /// Adds a line of input. A line ending in `\` outside a string is
+    /// joined to the next one, without the backslash or line break.
+    pub fn push_line(&mut self, line: &str) -> Input {
+        if self.is_pending() && line.trim().is_empty() {
+            self.clear();
+            return Input::Aborted;
+        }
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        if let Some(text) = text.strip_suffix('\\') {
+            let joined = format!("{}{}", self.pending, text);
+            if !in_string(&joined) {
+                self.pending = joined;
+                return Input::Incomplete;
+            }
+        }
+
+        self.pending.push_str(line);
+        if !self.pending.ends_with('\n') {
+            self.pending.push('\n');
+        }
+
+        if is_incomplete(&self.pending) {
+            Input::Incomplete
+        } else {
+            Input::Complete(std::mem::take(&mut self.pending))
+        }
+    }
+
+    /// Drops any pending lines, as when input ends mid-statement.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// A REPL line starting with `:`, which controls the REPL itself rather
+/// than running as Lox.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Quit,
+    Help,
+    /// Print the tokens of the rest of the line.
+    Tokens(String),
+    /// Any other name, without its `:`.
+    Unknown(String),
+}
+
+/// The text `:help` prints.
+pub const HELP: &str = "\
+:help            Show this list of commands.
+:quit, :q        Exit the REPL.
+:tokens <code>   Print the tokens scanned from <code>.";
+
+/// Recognizes a meta-command, or returns `None` for an ordinary line.
+pub fn meta_command(line: &str) -> Option<Command> {
+    let rest = line.trim().strip_prefix(':')?;
+    let (name, argument) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    Some(match name {
+        "quit" | "q" => Command::Quit,
+        "help" => Command::Help,
+        "tokens" => Command::Tokens(argument.trim().to_string()),
+        _ => Command::Unknown(name.to_string()),
+    })
+}
+
+/// Parses `source` as a bare expression with no trailing `;`, which the
+/// REPL evaluates and echoes. Returns `None` for anything else, including
+/// invalid input, which should then run as a program.
+pub fn bare_expression(source: &str) -> Option<Expr> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens_checked().ok()?.clone();
+    Parser::new(tokens).parse_expression().ok()
+}
+
+/// Whether `source` stops partway through a bracketed construct, a string,
+/// or a block comment. Anything else, including input with unrelated
+/// errors, counts as complete so that its errors get reported.
+fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+
+    scanner.unterminated().is_some() || !scanner.open_brackets().is_empty()
+}
+
+/// Whether `source` ends partway through a string literal.
+fn in_string(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+
+    scanner.unterminated() == Some(Unterminated::String)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_is_complete_only_after_closing_brace() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("fun f() {\n"), Input::Incomplete);
+        assert_eq!(buffer.prompt(), "... ");
+        assert_eq!(buffer.push_line("  print 1;\n"), Input::Incomplete);
+        assert_eq!(
+            buffer.push_line("}\n"),
+            Input::Complete("fun f() {\n  print 1;\n}\n".to_string())
+        );
+        assert!(!buffer.is_pending());
+        assert_eq!(buffer.prompt(), "> ");
+    }
+
+    #[test]
+    fn array_is_complete_only_after_closing_bracket() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("var a = [1,\n"), Input::Incomplete);
+        assert_eq!(
+            buffer.push_line("2];\n"),
+            Input::Complete("var a = [1,\n2];\n".to_string())
+        );
+    }
+
+    #[test]
+    fn single_line_is_complete() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(
+            buffer.push_line("print 1;\n"),
+            Input::Complete("print 1;\n".to_string())
+        );
+        // Unbalanced closers are an error to report, not a reason to wait.
+        assert_eq!(buffer.push_line("}\n"), Input::Complete("}\n".to_string()));
+    }
+
+    #[test]
+    fn unterminated_string_and_comment_wait_for_more() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("print \"a\n"), Input::Incomplete);
+        assert!(matches!(buffer.push_line("b\";\n"), Input::Complete(_)));
+        assert_eq!(buffer.push_line("var s = \"\"\"\n"), Input::Incomplete);
+        assert!(matches!(buffer.push_line("\"\"\";\n"), Input::Complete(_)));
+        assert_eq!(buffer.push_line("/* note\n"), Input::Incomplete);
+        assert!(matches!(buffer.push_line("*/\n"), Input::Complete(_)));
+    }
+
+    #[test]
+    fn bare_expression_excludes_statements() {
+        assert!(bare_expression("1 + 2\n").is_some());
+        assert!(bare_expression("1 + 2;\n").is_none());
+        assert!(bare_expression("var a = 1;\n").is_none());
+        assert!(bare_expression("1 +\n").is_none());
+    }
+
+    #[test]
+    fn meta_command_dispatch() {
+        assert_eq!(meta_command(":quit\n"), Some(Command::Quit));
+        assert_eq!(meta_command("  :q"), Some(Command::Quit));
+        assert_eq!(meta_command(":help"), Some(Command::Help));
+        assert_eq!(
+            meta_command(":tokens 1 + x\n"),
+            Some(Command::Tokens("1 + x".to_string()))
+        );
+        assert_eq!(
+            meta_command(":frobnicate now"),
+            Some(Command::Unknown("frobnicate".to_string()))
+        );
+        assert_eq!(meta_command("print 1;\n"), None);
+    }
+
+    #[test]
+    fn trailing_backslash_joins_lines() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("print 1 +\\\n"), Input::Incomplete);
+        assert_eq!(buffer.prompt(), "... ");
+        assert_eq!(
+            buffer.push_line("  2;\n"),
+            Input::Complete("print 1 +  2;\n".to_string())
+        );
+    }
+
+    #[test]
+    fn backslash_in_string_does_not_continue() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("print \"a\\\n"), Input::Incomplete);
+        assert_eq!(
+            buffer.push_line("b\";\n"),
+            Input::Complete("print \"a\\\nb\";\n".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_continuation_aborts() {
+        let mut buffer = LineBuffer::new();
+
+        assert_eq!(buffer.push_line("{\n"), Input::Incomplete);
+        assert_eq!(buffer.push_line("\n"), Input::Aborted);
+        assert!(!buffer.is_pending());
+        assert_eq!(buffer.push_line("\n"), Input::Complete("\n".to_string()));
+    }
+}
@@ -0,0 +1,455 @@
+use crate::interpreter::Interpreter;
+use crate::parser::{Expr, ExprId, Stmt};
+use crate::token::Token;
+
+use std::collections::HashMap;
+
+/// A static error found while resolving variables, such as reading a local
+/// in its own initializer.
+#[derive(Debug)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl ResolveError {
+    fn new(token: &Token, message: &str) -> Self {
+        ResolveError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The kind of function whose body is being resolved.
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+/// The kind of class whose body is being resolved.
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Walks a parsed program and tells the interpreter how many scopes out
+/// each local variable reference lives. References that are not found in
+/// any local scope are left unresolved and looked up as globals.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    /// One map per enclosing local scope, innermost last. A name maps to
+    /// `false` while it is declared but its initializer is still running.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ResolveError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Resolver {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves every statement, collecting all errors rather than stopping
+    /// at the first one.
+    pub fn resolve(mut self, statements: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    /// Resolves a lone expression, as typed at the REPL.
+    pub fn resolve_expr(mut self, expr: &Expr) -> Result<(), Vec<ResolveError>> {
+        self.resolve_expression(expr);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) | Stmt::Write(expr) => {
+                self.resolve_expression(expr)
+            }
+            Stmt::Return { keyword, value } => {
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let enclosing_class = std::mem::replace(&mut self.current_class, ClassType::Class);
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                        && superclass_name.lexeme == name.lexeme
+                    {
+                        self.error(superclass_name, "A class can't inherit from itself.");
+                    }
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expression(superclass);
+
+                    self.begin_scope();
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert("super".to_string(), true);
+                    }
+                }
+
+                self.begin_scope();
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert("this".to_string(), true);
+                }
+                for method in methods {
+                    if let Stmt::Function { name, params, body } = method {
+                        let kind = if &*name.lexeme == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+                        self.resolve_function(params, body, kind);
+                    }
+                }
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            }
+            Stmt::Function { name, params, body } => {
+                // Defined before the body is resolved so the function can
+                // refer to itself recursively.
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::Const { name, initializer } => {
+                self.declare(name);
+                self.resolve_expression(initializer);
+                self.define(name);
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(body);
+            }
+            Stmt::Foreach {
+                name,
+                collection,
+                body,
+                ..
+            } => {
+                self.resolve_expression(collection);
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.resolve_statement(body);
+                self.end_scope();
+            }
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => {
+                self.resolve_expression(discriminant);
+                for (value, body) in cases {
+                    self.resolve_expression(value);
+                    self.begin_scope();
+                    self.resolve_statements(body);
+                    self.end_scope();
+                }
+                if let Some(body) = default {
+                    self.begin_scope();
+                    self.resolve_statements(body);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], kind: FunctionType) {
+        let enclosing_function = std::mem::replace(&mut self.current_function, kind);
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(body);
+        self.end_scope();
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable { id, name } => {
+                let initializing = self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.get(&*name.lexeme) == Some(&false));
+                if initializing {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                self.resolve_local(*id, name);
+            }
+            Expr::Assign { id, name, value } => {
+                self.resolve_expression(value);
+                self.resolve_local(*id, name);
+            }
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expr::Get { object, .. } => self.resolve_expression(object),
+            Expr::Array { elements, .. } => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+                self.resolve_expression(value);
+            }
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expr::This { id, keyword } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::Super { id, keyword, .. } => match self.current_class {
+                ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                ClassType::Class => {
+                    self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                }
+                ClassType::Subclass => self.resolve_local(*id, keyword),
+            },
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_expression(then_expr);
+                self.resolve_expression(else_expr);
+            }
+            Expr::Function { params, body, .. } => {
+                self.resolve_function(params, body, FunctionType::Function)
+            }
+            Expr::Grouping { expression, .. } => self.resolve_expression(expression),
+            Expr::Literal { .. } => {}
+            Expr::Unary { right, .. } => self.resolve_expression(right),
+        }
+    }
+
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&*name.lexeme));
+        if let Some(depth) = found {
+            self.interpreter.resolve(id, depth);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.contains_key(&*name.lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+        scope.insert(name.lexeme.to_string(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.to_string(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolveError::new(token, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Result<(), Vec<ResolveError>> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Resolver::new(&mut Interpreter::new()).resolve(&statements)
+    }
+
+    #[test]
+    fn resolve_rejects_read_in_own_initializer() {
+        let errors = resolve("var a = 1; { var a = a; }").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "Can't read local variable in its own initializer."
+        );
+        assert_eq!(errors[0].token.column, 22);
+    }
+
+    #[test]
+    fn resolve_allows_global_redeclaration() {
+        assert!(resolve("var a = 1; var a = a;").is_ok());
+    }
+
+    #[test]
+    fn resolve_rejects_duplicate_local() {
+        let errors = resolve("fun f(a) { var b; var b; } { var c; { var c; } }").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "Already a variable with this name in this scope."
+        );
+        assert_eq!(&*errors[0].token.lexeme, "b");
+    }
+
+    #[test]
+    fn resolve_rejects_value_return_from_initializer() {
+        let errors = resolve("class A { init() { return 1; } }").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "Can't return a value from an initializer."
+        );
+
+        assert!(resolve("class A { init() { return; } }").is_ok());
+    }
+
+    #[test]
+    fn resolve_rejects_self_inheritance() {
+        let errors = resolve("class A < A {}").unwrap_err();
+
+        assert_eq!(errors[0].message, "A class can't inherit from itself.");
+    }
+
+    #[test]
+    fn resolve_rejects_misplaced_super() {
+        let errors = resolve("class A { f() { super.f(); } } fun g() { super.g(); }").unwrap_err();
+
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Can't use 'super' in a class with no superclass.",
+                "Can't use 'super' outside of a class."
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_rejects_this_outside_class() {
+        let errors = resolve("fun f() { print this; }").unwrap_err();
+
+        assert_eq!(errors[0].message, "Can't use 'this' outside of a class.");
+    }
+}
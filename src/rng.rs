@@ -0,0 +1,81 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small xorshift64* pseudo-random number generator. It is fast and
+/// reproducible from a seed, but not suitable for cryptography.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    // Never zero, which xorshift can't leave.
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator whose sequence is determined by `seed`. Nearby
+    /// seeds are scrambled first, so they still give unrelated sequences.
+    pub fn new(seed: u64) -> Self {
+        let state = splitmix64(seed);
+        Rng {
+            state: if state == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                state
+            },
+        }
+    }
+
+    /// Creates a generator seeded from the current time.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        Rng::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A float in `[0, 1)`, built from the top 53 bits of the next number
+    /// so that every value is equally likely.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        let sequence: Vec<u64> = (0..4).map(|_| first.next_u64()).collect();
+
+        assert_eq!(
+            sequence,
+            (0..4).map(|_| second.next_u64()).collect::<Vec<_>>()
+        );
+        assert_ne!(sequence[0], sequence[1]);
+        assert_ne!(Rng::new(43).next_u64(), sequence[0]);
+    }
+
+    #[test]
+    fn floats_are_in_unit_interval() {
+        let mut rng = Rng::new(0);
+
+        for _ in 0..1000 {
+            let value = rng.next_f64();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+}
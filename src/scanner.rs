@@ -1,16 +1,75 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::literal::Literal;
 use crate::token::Token;
 use crate::token_type::TokenType;
 
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
 pub struct Scanner<'a> {
     source: &'a str,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: usize,
-    errors: Vec<(usize, String)>,
+    column: usize,
+    start_column: usize,
+    diagnostics: Vec<Diagnostic>,
+    // The current run of adjacent unexpected characters and where it ends.
+    unexpected: Vec<char>,
+    unexpected_end: usize,
+    // Whether whitespace and comments are kept as tokens.
+    lossless: bool,
+    // One shared allocation per distinct lexeme.
+    lexemes: HashMap<&'a str, Rc<str>>,
+    // How many columns a tab advances.
+    tab_width: usize,
+    // The file being scanned, for diagnostics.
+    file_name: Option<&'a str>,
+    // The brackets opened and not yet closed, innermost last.
+    openers: Vec<char>,
+    max_depth: usize,
+    depth_limit: usize,
+    max_source_bytes: Option<usize>,
+    // Whether `print` is the statement keyword or an ordinary identifier.
+    print_as_keyword: bool,
+    stats: ScanStats,
+    // What the source ended inside of, if anything.
+    unterminated: Option<Unterminated>,
+}
+
+/// A construct that the source ended before closing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unterminated {
+    /// A string literal of any kind: plain, raw, or block.
+    String,
+    BlockComment,
+}
+
+/// Summary counts from a scan, for tooling and benchmarks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScanStats {
+    /// Every token produced, including the final `Eof`.
+    pub token_count: usize,
+    /// The lines in the source, counted as `str::lines` would.
+    pub line_count: usize,
+    pub string_count: usize,
+    pub number_count: usize,
+    pub identifier_count: usize,
 }
 
+/// How many columns a tab advances by default.
+pub const DEFAULT_TAB_WIDTH: usize = 1;
+
+/// How deeply brackets may nest by default before scanning reports an
+/// error, well short of what would overflow the recursive-descent parser.
+pub const DEFAULT_DEPTH_LIMIT: usize = 256;
+
 impl<'a> Scanner<'a> {
+    /// Creates a scanner over `source`. A leading UTF-8 byte-order mark is
+    /// skipped without moving the column, though spans still index into
+    /// `source` itself.
     pub fn new(source: &'a str) -> Self {
         Scanner {
             source,
@@ -18,90 +77,339 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
-            errors: Vec::new(),
+            column: 1,
+            start_column: 1,
+            diagnostics: Vec::new(),
+            unexpected: Vec::new(),
+            unexpected_end: 0,
+            lossless: false,
+            lexemes: HashMap::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            file_name: None,
+            openers: Vec::new(),
+            max_depth: 0,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            max_source_bytes: None,
+            print_as_keyword: true,
+            stats: ScanStats::default(),
+            unterminated: None,
+        }
+    }
+
+    /// Creates a scanner that also emits `Whitespace` and `Comment` tokens
+    /// and gives every token its exact source text as its lexeme, quotes
+    /// included, so concatenating the lexemes reproduces the source. Text
+    /// that fails to scan is the only thing left out.
+    pub fn new_lossless(source: &'a str) -> Self {
+        Scanner {
+            lossless: true,
+            ..Scanner::new(source)
         }
     }
 
+    /// Counts each tab as `tab_width` columns rather than one, so reported
+    /// columns match what a terminal or editor shows.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Names the file the source came from, so diagnostics can say where
+    /// each error is.
+    pub fn with_file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    pub fn file_name(&self) -> Option<&'a str> {
+        self.file_name
+    }
+
+    /// Sets how deeply `(`, `[`, and `{` may nest before scanning records an
+    /// error.
+    pub fn with_depth_limit(mut self, depth_limit: usize) -> Self {
+        self.depth_limit = depth_limit;
+        self
+    }
+
+    /// Refuses to scan a source longer than `max_source_bytes`, recording an
+    /// error instead. By default any size is scanned.
+    pub fn with_max_source_bytes(mut self, max_source_bytes: usize) -> Self {
+        self.max_source_bytes = Some(max_source_bytes);
+        self
+    }
+
+    /// Chooses whether `print` scans as the `Print` keyword, which it does
+    /// by default as in standard Lox, or as an `Identifier`, so that code
+    /// can name a variable or function `print`. `write` is unaffected.
+    pub fn with_print_as_keyword(mut self, print_as_keyword: bool) -> Self {
+        self.print_as_keyword = print_as_keyword;
+        self
+    }
+
+    /// Points the scanner at a new `source`, discarding the tokens,
+    /// diagnostics, and interned lexemes from the last scan while keeping
+    /// their allocations and every setting chosen with the `with_*`
+    /// builders, so one scanner can be reused across many snippets.
+    ///
+    /// Tokens borrowed from the previous scan are invalidated; clone any
+    /// that need to outlive the reset.
+    pub fn reset(&mut self, source: &'a str) {
+        self.source = source;
+        self.tokens.clear();
+        self.diagnostics.clear();
+        self.lexemes.clear();
+        self.start = 0;
+        self.current = 0;
+        self.line = 1;
+        self.column = 1;
+        self.start_column = 1;
+        self.unexpected.clear();
+        self.unexpected_end = 0;
+        self.openers.clear();
+        self.max_depth = 0;
+        self.stats = ScanStats::default();
+        self.unterminated = None;
+    }
+
+    /// The deepest bracket nesting seen so far.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The string or block comment that the last scan ended inside of,
+    /// which is also reported as an error.
+    pub fn unterminated(&self) -> Option<Unterminated> {
+        self.unterminated
+    }
+
+    /// The brackets opened and not yet closed, innermost last.
+    pub fn open_brackets(&self) -> &[char] {
+        &self.openers
+    }
+
+    /// Counts of what the last scan produced.
+    pub fn stats(&self) -> ScanStats {
+        self.stats
+    }
+
     pub fn scan_tokens(&mut self) -> &Vec<Token> {
+        self.check_source_size();
         while !self.is_at_end() {
-            // We are at the beginning of the next lexeme.
-            self.start = self.current;
-            self.scan_token();
+            self.scan_next();
+        }
+
+        let eof = self.eof_token();
+        self.tokens.push(eof);
+        self.stats.token_count += 1;
+        self.stats.line_count = if self.source.trim_start_matches('\u{feff}').is_empty() {
+            0
+        } else {
+            self.line - usize::from(self.source.ends_with('\n'))
+        };
+
+        &self.tokens
+    }
+
+    /// Skips straight to the end of a source over the size limit, having
+    /// recorded an error, so that nothing in it is scanned.
+    fn check_source_size(&mut self) {
+        if let Some(limit) = self.max_source_bytes
+            && self.source.len() > limit
+            && !self.is_at_end()
+        {
+            self.error(format!("Source is larger than the {} byte limit.", limit));
+            self.current = self.source.len();
+        }
+    }
+
+    fn scan_next(&mut self) {
+        // We are at the beginning of the next lexeme.
+        self.start = self.current;
+        self.start_column = self.column;
+        let scanned = self.tokens.len();
+        self.scan_token();
+
+        for token in &self.tokens[scanned..] {
+            self.stats.token_count += 1;
+            match token.token_type {
+                TokenType::String => self.stats.string_count += 1,
+                TokenType::Number => self.stats.number_count += 1,
+                TokenType::Identifier => self.stats.identifier_count += 1,
+                _ => {}
+            }
+        }
+
+        if self.lossless {
+            for index in scanned..self.tokens.len() {
+                let text = &self.source[self.tokens[index].span.clone()];
+                self.tokens[index].lexeme = self.intern(text);
+            }
         }
+    }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
+    fn eof_token(&mut self) -> Token {
+        Token::new(
+            TokenType::Eof,
+            self.intern(""),
+            None,
+            self.line,
+            self.column,
+            self.current..self.current,
+        )
+    }
 
+    pub fn tokens(&self) -> &[Token] {
         &self.tokens
     }
 
+    /// The scanned tokens as a JSON array, for external tooling.
+    pub fn tokens_to_json(&self) -> String {
+        let tokens: Vec<String> = self.tokens.iter().map(Token::to_json).collect();
+        format!("[{}]", tokens.join(","))
+    }
+
+    /// The errors and warnings recorded so far, in source order.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Scans like `scan_tokens`, but returns the recorded errors instead of
+    /// the tokens if any were encountered along the way.
+    pub fn scan_tokens_checked(&mut self) -> Result<&Vec<Token>, &[Diagnostic]> {
+        self.scan_tokens();
+
+        if !self.had_error() {
+            Ok(&self.tokens)
+        } else {
+            Err(&self.diagnostics)
+        }
+    }
+
     fn scan_token(&mut self) {
         let c = self.advance();
+        self.track_nesting(c);
         match c {
-            b'(' => self.add_token(TokenType::LeftParen),
-            b')' => self.add_token(TokenType::RightParen),
-            b'{' => self.add_token(TokenType::LeftBrace),
-            b'}' => self.add_token(TokenType::RightBrace),
-            b',' => self.add_token(TokenType::Comma),
-            b'.' => self.add_token(TokenType::Dot),
-            b'-' => self.add_token(TokenType::Minus),
-            b'+' => self.add_token(TokenType::Plus),
-            b';' => self.add_token(TokenType::Semicolon),
-            b'*' => self.add_token(TokenType::Star),
-            b'!' => {
-                let token_type = if self.match_char(b'=') {
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => self.add_token(TokenType::LeftBrace),
+            '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ',' => self.add_token(TokenType::Comma),
+            '.' => self.add_token(TokenType::Dot),
+            '-' => self.add_operator(TokenType::Minus, TokenType::MinusEqual),
+            '%' => self.add_operator(TokenType::Percent, TokenType::PercentEqual),
+            '?' => self.add_token(TokenType::Question),
+            ':' => self.add_token(TokenType::Colon),
+            '+' => self.add_operator(TokenType::Plus, TokenType::PlusEqual),
+            ';' => self.add_token(TokenType::Semicolon),
+            '*' => {
+                if self.match_char('*') {
+                    self.add_token(TokenType::StarStar);
+                } else {
+                    self.add_operator(TokenType::Star, TokenType::StarEqual);
+                }
+            }
+            '!' => {
+                let token_type = if self.match_char('=') {
                     TokenType::BangEqual
                 } else {
                     TokenType::Bang
                 };
                 self.add_token(token_type);
             }
-            b'=' => {
-                let token_type = if self.match_char(b'=') {
+            '=' => {
+                let token_type = if self.match_char('=') {
                     TokenType::EqualEqual
                 } else {
                     TokenType::Equal
                 };
                 self.add_token(token_type);
             }
-            b'<' => {
-                let token_type = if self.match_char(b'=') {
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            '<' => {
+                let token_type = if self.match_char('=') {
                     TokenType::LessEqual
+                } else if self.match_char('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 };
                 self.add_token(token_type);
             }
-            b'>' => {
-                let token_type = if self.match_char(b'=') {
+            '>' => {
+                let token_type = if self.match_char('=') {
                     TokenType::GreaterEqual
+                } else if self.match_char('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 };
                 self.add_token(token_type);
             }
-            b'/' => {
-                if self.match_char(b'/') {
+            '/' => {
+                if self.match_char('/') {
                     // A comment goes until the end of the line.
-                    while self.peek() != b'\n' && !self.is_at_end() {
+                    while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_trivia(TokenType::Comment);
+                } else if self.match_char('*') {
+                    let diagnostics = self.diagnostics.len();
+                    self.block_comment();
+                    if self.diagnostics.len() == diagnostics {
+                        self.add_trivia(TokenType::Comment);
+                    }
                 } else {
-                    self.add_token(TokenType::Slash);
+                    self.add_operator(TokenType::Slash, TokenType::SlashEqual);
                 }
             }
-            b' ' | b'\r' | b'\t' => {
-                // Ignore whitespace.
+            // A byte-order mark takes no columns. A lossless scan keeps it
+            // as whitespace so that the source can still be reproduced.
+            '\u{feff}' if self.start == 0 => {
+                self.column = 1;
+                self.add_trivia(TokenType::Whitespace);
             }
-            b'\n' => {
-                self.line += 1;
+            ' ' | '\r' | '\t' | '\n' => {
+                if c == '\n' {
+                    self.line += 1;
+                }
+                // Take the whole run at once so a lossless scan yields one
+                // token per run.
+                while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+                    if self.advance() == '\n' {
+                        self.line += 1;
+                    }
+                }
+                self.add_trivia(TokenType::Whitespace);
+            }
+            '"' if self.peek() == '"' && self.peek_next() == '"' => {
+                // Consume the rest of the opening """.
+                self.advance();
+                self.advance();
+                self.block_string();
+            }
+            '"' => self.string(),
+            '\'' => self.char_literal(),
+            'r' if self.peek() == '"' => {
+                // Consume the opening ".
+                self.advance();
+                self.raw_string();
             }
-            b'"' => self.string(),
             ch if ch.is_ascii_digit() => self.number(),
-            ch if ch.is_ascii_alphabetic() || ch == b'_' => self.identifier(),
-            _ => self
-                .errors
-                .push((self.line, format!("Unexpected character: {}", c as char))),
+            ch if is_identifier_start(ch) => self.identifier(),
+            // `advance` consumed all of `c`, however many bytes it takes, so
+            // scanning resumes on a character boundary.
+            _ => self.unexpected_character(c),
         }
     }
 
@@ -109,159 +417,686 @@ impl<'a> Scanner<'a> {
         self.current >= self.source.len()
     }
 
-    fn advance(&mut self) -> u8 {
-        let c = self.source.as_bytes()[self.current];
-        self.current += 1;
+    fn advance(&mut self) -> char {
+        let c = self.peek();
+        self.current += c.len_utf8();
+        match c {
+            '\n' => self.column = 1,
+            '\t' => self.column += self.tab_width,
+            _ => self.column += 1,
+        }
 
         c
     }
 
     fn add_token(&mut self, token_type: TokenType) {
         let text = &self.source[self.start..self.current];
-        self.tokens
-            .push(Token::new(token_type, text.to_string(), None, self.line));
+        let lexeme = self.intern(text);
+        self.tokens.push(Token::new(
+            token_type,
+            lexeme,
+            None,
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ));
+    }
+
+    /// Returns the shared copy of `text`, so that repeated lexemes such as
+    /// keywords and variable names are only allocated once per scan.
+    fn intern(&mut self, text: &'a str) -> Rc<str> {
+        Rc::clone(self.lexemes.entry(text).or_insert_with(|| Rc::from(text)))
+    }
+
+    /// Adds `with_equal` if the operator just scanned is followed by `=`,
+    /// and `alone` otherwise.
+    fn add_operator(&mut self, alone: TokenType, with_equal: TokenType) {
+        let token_type = if self.match_char('=') {
+            with_equal
+        } else {
+            alone
+        };
+        self.add_token(token_type);
+    }
+
+    /// Adds a whitespace or comment token, but only when scanning
+    /// losslessly.
+    fn add_trivia(&mut self, token_type: TokenType) {
+        if self.lossless {
+            self.add_token(token_type);
+        }
+    }
+
+    /// Updates the bracket nesting for `c`. Beyond the depth limit, or at a
+    /// closer that doesn't match, a diagnostic is recorded; a mismatch is
+    /// only a warning, since the parser reports the resulting syntax error.
+    fn track_nesting(&mut self, c: char) {
+        let expected_opener = match c {
+            '(' | '[' | '{' => {
+                self.openers.push(c);
+                if self.openers.len() > self.max_depth {
+                    self.max_depth = self.openers.len();
+                    if self.max_depth == self.depth_limit + 1 {
+                        self.error(format!(
+                            "Nesting is too deep (more than {} levels).",
+                            self.depth_limit
+                        ));
+                    }
+                }
+                return;
+            }
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            _ => return,
+        };
+
+        match self.openers.pop() {
+            Some(opener) if opener == expected_opener => {}
+            Some(opener) => {
+                let message = format!("Mismatched '{}'; expected a closer for '{}'.", c, opener);
+                self.warning_at(self.line, self.start_column, message);
+            }
+            None => {
+                self.warning_at(self.line, self.start_column, format!("Unmatched '{}'.", c));
+            }
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.error_at(self.line, self.start_column, message);
+    }
+
+    fn error_at(&mut self, line: usize, column: usize, message: String) {
+        self.diagnostics
+            .push(Diagnostic::error(line, column, message));
+    }
+
+    fn warning_at(&mut self, line: usize, column: usize, message: String) {
+        self.diagnostics
+            .push(Diagnostic::warning(line, column, message));
+    }
+
+    fn unexpected_character(&mut self, c: char) {
+        let continues_run = !self.unexpected.is_empty() && self.unexpected_end == self.start;
+        if !continues_run {
+            self.unexpected.clear();
+        }
+        self.unexpected.push(c);
+        self.unexpected_end = self.current;
+
+        if !continues_run {
+            self.error(format!("Unexpected character: {}", c));
+            return;
+        }
+
+        let characters = self
+            .unexpected
+            .iter()
+            .map(char::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Some(error) = self.diagnostics.last_mut() {
+            error.message = format!("Unexpected characters: {}", characters);
+        }
     }
 
-    fn match_char(&mut self, expected: u8) -> bool {
+    fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
         }
-        if self.source.as_bytes()[self.current] != expected {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.advance();
         true
     }
 
-    fn peek(&self) -> u8 {
-        if self.is_at_end() {
-            return b'\0';
-        }
-        self.source.as_bytes()[self.current]
+    fn peek(&self) -> char {
+        self.peek_at(0)
+    }
+
+    fn peek_next(&self) -> char {
+        self.peek_at(1)
+    }
+
+    /// Returns the character `offset` characters past the current one, or
+    /// `'\0'` beyond the end of the source. Offsets count characters, not
+    /// bytes, so lookahead never splits a multi-byte character.
+    fn peek_at(&self, offset: usize) -> char {
+        self.source[self.current..]
+            .chars()
+            .nth(offset)
+            .unwrap_or('\0')
     }
 
-    fn peek_next(&self) -> u8 {
-        if self.current + 1 >= self.source.len() {
-            return b'\0';
+    fn block_comment(&mut self) {
+        let start_line = self.line;
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.unterminated = Some(Unterminated::BlockComment);
+                self.error_at(
+                    start_line,
+                    self.start_column,
+                    "Unterminated block comment.".to_string(),
+                );
+                return;
+            }
+
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                self.advance();
+                depth -= 1;
+            } else {
+                if self.peek() == '\n' {
+                    self.line += 1;
+                }
+                self.advance();
+            }
         }
-        self.source.as_bytes()[self.current + 1]
     }
 
     fn string(&mut self) {
-        while self.peek() != b'"' && !self.is_at_end() {
-            if self.peek() == b'\n' {
+        let start_line = self.line;
+        let mut value = String::new();
+        let mut segment_start = self.current;
+
+        while self.peek() != '"' && !self.is_at_end() {
+            match self.peek() {
+                '\n' => {
+                    self.line += 1;
+                    self.advance();
+                }
+                '\\' => {
+                    value.push_str(&self.source[segment_start..self.current]);
+                    // The backslash.
+                    self.advance();
+                    if self.is_at_end() {
+                        break;
+                    }
+                    if let Some(escaped) = self.escape(self.peek()) {
+                        value.push(escaped);
+                        self.advance();
+                    }
+                    segment_start = self.current;
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+
+        if self.is_at_end() {
+            self.unterminated = Some(Unterminated::String);
+            self.error_at(
+                start_line,
+                self.start_column,
+                "Unterminated string.".to_string(),
+            );
+            return;
+        }
+
+        value.push_str(&self.source[segment_start..self.current]);
+
+        // The closing ".
+        self.advance();
+
+        // Trim the surrounding quotes.
+        let lexeme = &self.source[self.start + 1..self.current - 1];
+        let lexeme = self.intern(lexeme);
+        self.tokens.push(Token::new(
+            TokenType::String,
+            lexeme,
+            Some(Literal::Str(value)),
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ));
+    }
+
+    fn raw_string(&mut self) {
+        let start_line = self.line;
+
+        while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '\n' {
                 self.line += 1;
             }
             self.advance();
         }
 
         if self.is_at_end() {
-            self.errors
-                .push((self.line, "Unterminated string.".to_string()));
+            self.unterminated = Some(Unterminated::String);
+            self.error_at(
+                start_line,
+                self.start_column,
+                "Unterminated raw string.".to_string(),
+            );
             return;
         }
 
         // The closing ".
         self.advance();
 
-        // Trim the surrounding quotes.
-        let value = &self.source[self.start + 1..self.current - 1];
-        // self.add_token(TokenType::String);
+        // Trim the r prefix and the surrounding quotes.
+        let value = &self.source[self.start + 2..self.current - 1];
+        let lexeme = self.intern(value);
         self.tokens.push(Token::new(
             TokenType::String,
-            value.to_string(),
-            Some(value.to_string()),
+            lexeme,
+            Some(Literal::Str(value.to_string())),
             self.line,
+            self.start_column,
+            self.start..self.current,
         ));
     }
 
-    fn number(&mut self) {
-        while self.peek().is_ascii_digit() {
+    /// Scans a `"""` block string. Like a raw string, its text is taken
+    /// as written, without escapes, and a newline straight after the
+    /// opening delimiter is dropped. When the closing delimiter sits on a
+    /// line of its own, that line is dropped too, and its indentation is
+    /// removed from the start of every line.
+    fn block_string(&mut self) {
+        let start_line = self.line;
+
+        while !self.source[self.current..].starts_with("\"\"\"") && !self.is_at_end() {
+            if self.peek() == '\n' {
+                self.line += 1;
+            }
             self.advance();
         }
 
+        if self.is_at_end() {
+            self.unterminated = Some(Unterminated::String);
+            self.error_at(
+                start_line,
+                self.start_column,
+                "Unterminated block string.".to_string(),
+            );
+            return;
+        }
+
+        let text = &self.source[self.start + 3..self.current];
+        // The closing """.
+        for _ in 0..3 {
+            self.advance();
+        }
+
+        let lexeme = self.intern(text);
+        self.tokens.push(Token::new(
+            TokenType::String,
+            lexeme,
+            Some(Literal::Str(dedent(text))),
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ));
+    }
+
+    fn char_literal(&mut self) {
+        let value = match self.peek() {
+            '\'' => {
+                self.advance();
+                self.error("Empty character literal.".to_string());
+                return;
+            }
+            '\\' => {
+                self.advance();
+                let c = self.peek();
+                let escaped = self.escape(c);
+                if !self.is_at_end() {
+                    self.advance();
+                }
+                escaped
+            }
+            '\n' => None,
+            _ if self.is_at_end() => None,
+            _ => Some(self.advance()),
+        };
+
+        if !self.match_char('\'') {
+            // Skip the rest of the literal so scanning resumes after it.
+            while self.peek() != '\'' && self.peek() != '\n' && !self.is_at_end() {
+                self.advance();
+            }
+            if self.match_char('\'') {
+                self.error("Character literal must contain exactly one character.".to_string());
+            } else {
+                self.error("Unterminated character literal.".to_string());
+            }
+            return;
+        }
+
+        if let Some(value) = value {
+            // Trim the surrounding quotes.
+            let lexeme = &self.source[self.start + 1..self.current - 1];
+            let lexeme = self.intern(lexeme);
+            self.tokens.push(Token::new(
+                TokenType::Char,
+                lexeme,
+                Some(Literal::Char(value)),
+                self.line,
+                self.start_column,
+                self.start..self.current,
+            ));
+        }
+    }
+
+    fn escape(&mut self, c: char) -> Option<char> {
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            '0' => Some('\0'),
+            _ => {
+                // The backslash sits just before the current column.
+                self.error_at(
+                    self.line,
+                    self.column - 1,
+                    format!("Unknown escape sequence '\\{}'.", c),
+                );
+                None
+            }
+        }
+    }
+
+    fn number(&mut self) {
+        if &self.source[self.start..self.current] == "0" {
+            let radix = match self.peek() {
+                'x' | 'X' => Some((16, "hexadecimal")),
+                'b' | 'B' => Some((2, "binary")),
+                'o' | 'O' => Some((8, "octal")),
+                _ => None,
+            };
+            if let Some((radix, name)) = radix {
+                // Consume the base prefix.
+                self.advance();
+                self.radix_number(radix, name);
+                return;
+            }
+        }
+
+        self.digits();
+
         // Look for a fractional part.
-        if self.peek() == b'.' && self.peek_next().is_ascii_digit() {
+        if self.peek() == '.' && (self.peek_next().is_ascii_digit() || self.peek_next() == '_') {
             // Consume the "."
             self.advance();
+            self.digits();
+        } else if self.peek() == '.' {
+            // `123.` is not a valid literal, but it is clear what was meant,
+            // so the dot is taken as part of the number rather than left
+            // behind as a confusing `Dot` token.
+            self.warning_at(
+                self.line,
+                self.column,
+                "A number literal cannot end with '.'.".to_string(),
+            );
+            self.advance();
+        }
 
-            while self.peek().is_ascii_digit() {
+        // Look for an exponent part.
+        if self.peek() == 'e' || self.peek() == 'E' {
+            // Consume the "e" and an optional sign.
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
                 self.advance();
             }
+
+            if !self.peek().is_ascii_digit() {
+                self.error("Expect digits in exponent.".to_string());
+                return;
+            }
+            self.digits();
+        }
+
+        let lexeme = &self.source[self.start..self.current];
+        if !separators_are_valid(lexeme) {
+            self.error("Digit separator '_' must appear between two digits.".to_string());
+            return;
+        }
+
+        let digits = lexeme.replace('_', "");
+        let integer = !digits.contains(['.', 'e', 'E']);
+        let value = match digits.parse::<i64>() {
+            Ok(value) if integer => Literal::Int(value),
+            // Too large for an `i64`, or written with a fraction or exponent.
+            _ => Literal::Number(digits.parse::<f64>().unwrap()),
+        };
+        let lexeme = self.intern(lexeme);
+        self.tokens.push(Token::new(
+            TokenType::Number,
+            lexeme,
+            Some(value),
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ));
+    }
+
+    /// Consumes a run of decimal digits, including `_` separators.
+    fn digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    fn radix_number(&mut self, radix: u32, name: &str) {
+        let digits_start = self.current;
+        while self.peek().is_ascii_alphanumeric() {
+            self.advance();
+        }
+
+        let digits = &self.source[digits_start..self.current];
+        if digits.is_empty() {
+            self.error(format!("Expect digits after {} prefix.", name));
+            return;
+        }
+        if let Some(invalid) = digits.chars().find(|c| !c.is_digit(radix)) {
+            self.error(format!("Invalid digit '{}' in {} literal.", invalid, name));
+            return;
         }
 
-        let value = &self.source[self.start..self.current];
-        // self.add_token(TokenType::Number);
+        let value = match u64::from_str_radix(digits, radix) {
+            Ok(value) => value,
+            Err(_) => {
+                self.error(format!("The {} literal is too large.", name));
+                return;
+            }
+        };
+
+        let lexeme = &self.source[self.start..self.current];
+        let lexeme = self.intern(lexeme);
         self.tokens.push(Token::new(
             TokenType::Number,
-            value.to_string(),
-            Some(value.to_string()),
+            lexeme,
+            Some(i64::try_from(value).map_or(Literal::Number(value as f64), Literal::Int)),
             self.line,
+            self.start_column,
+            self.start..self.current,
         ));
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() || self.peek() == b'_' {
+        while is_identifier_continue(self.peek()) {
             self.advance();
         }
 
         let text = &self.source[self.start..self.current];
-        let token_type = match text {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "fun" => TokenType::Fun,
-            "for" => TokenType::For,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => TokenType::Identifier,
+        let token_type = match keywords().get(text) {
+            Some(TokenType::Print) if !self.print_as_keyword => TokenType::Identifier,
+            Some(&token_type) => token_type,
+            None => TokenType::Identifier,
         };
 
-        self.tokens
-            .push(Token::new(token_type, text.to_string(), None, self.line));
+        let lexeme = self.intern(text);
+
+        self.tokens.push(Token::new(
+            token_type,
+            lexeme,
+            None,
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ));
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// The reserved words, built once on first use.
+fn keywords() -> &'static HashMap<&'static str, TokenType> {
+    static KEYWORDS: OnceLock<HashMap<&'static str, TokenType>> = OnceLock::new();
+    KEYWORDS.get_or_init(|| {
+        HashMap::from([
+            ("and", TokenType::And),
+            ("break", TokenType::Break),
+            ("case", TokenType::Case),
+            ("class", TokenType::Class),
+            ("const", TokenType::Const),
+            ("continue", TokenType::Continue),
+            ("default", TokenType::Default),
+            ("else", TokenType::Else),
+            ("false", TokenType::False),
+            ("fun", TokenType::Fun),
+            ("for", TokenType::For),
+            ("foreach", TokenType::Foreach),
+            ("if", TokenType::If),
+            ("in", TokenType::In),
+            ("nil", TokenType::Nil),
+            ("or", TokenType::Or),
+            ("print", TokenType::Print),
+            ("return", TokenType::Return),
+            ("super", TokenType::Super),
+            ("switch", TokenType::Switch),
+            ("this", TokenType::This),
+            ("true", TokenType::True),
+            ("var", TokenType::Var),
+            ("while", TokenType::While),
+            ("write", TokenType::Write),
+        ])
+    })
+}
 
-    fn scan(source: &str) -> Scanner<'_> {
-        let mut scanner = Scanner::new(source);
-        scanner.scan_tokens();
-        scanner
-    }
+impl<'a> IntoIterator for Scanner<'a> {
+    type Item = Token;
+    type IntoIter = Tokens<'a>;
 
-    fn assert_token(
-        token: &Token,
-        token_type: TokenType,
-        lexeme: &str,
-        literal: Option<&str>,
-        line: usize,
-    ) {
-        assert!(token_type_eq(&token.token_type, &token_type));
-        assert_eq!(token.lexeme, lexeme);
-        assert_eq!(token.literal.as_deref(), literal);
-        assert_eq!(token.line, line);
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.check_source_size();
+        Tokens {
+            scanner: self,
+            finished: false,
+        }
     }
+}
 
-    fn token_type_eq(left: &TokenType, right: &TokenType) -> bool {
-        std::mem::discriminant(left) == std::mem::discriminant(right)
+/// A lazy token stream that scans one lexeme per call to `next`, finishing
+/// with a single `Eof` token.
+pub struct Tokens<'a> {
+    scanner: Scanner<'a>,
+    finished: bool,
+}
+
+impl Tokens<'_> {
+    /// The diagnostics recorded so far while streaming.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.scanner.diagnostics()
     }
+}
 
-    #[test]
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+
+        while self.scanner.tokens.is_empty() {
+            if self.scanner.is_at_end() {
+                self.finished = true;
+                return Some(self.scanner.eof_token());
+            }
+            self.scanner.scan_next();
+        }
+
+        Some(self.scanner.tokens.remove(0))
+    }
+}
+
+/// The value of a block string whose text between the delimiters is `text`.
+fn dedent(text: &str) -> String {
+    let text = text
+        .strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(text);
+    let Some((body, indent)) = text.rsplit_once('\n') else {
+        return text.to_string();
+    };
+    if !indent.chars().all(|c| c == ' ' || c == '\t') {
+        return text.to_string();
+    }
+
+    let lines: Vec<&str> = body
+        .split('\n')
+        .map(|line| line.strip_prefix(indent).unwrap_or(line.trim_start()))
+        .collect();
+    lines.join("\n")
+}
+
+/// Checks that every `_` in a number literal sits between two digits.
+fn separators_are_valid(lexeme: &str) -> bool {
+    let bytes = lexeme.as_bytes();
+    bytes.iter().enumerate().all(|(i, &byte)| {
+        byte != b'_'
+            || (i > 0
+                && bytes[i - 1].is_ascii_digit()
+                && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+    })
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Scanner<'_> {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens();
+        scanner
+    }
+
+    fn assert_token(
+        token: &Token,
+        token_type: TokenType,
+        lexeme: &str,
+        literal: Option<Literal>,
+        line: usize,
+    ) {
+        assert_eq!(token.token_type, token_type);
+        assert_eq!(&*token.lexeme, lexeme);
+        assert_eq!(token.literal, literal);
+        assert_eq!(token.line, line);
+    }
+
+    #[test]
     fn scan_single_char_tokens() {
-        let scanner = scan("(){}.,-+;*/");
+        let scanner = scan("(){}[].,-+;*/");
         let tokens = scanner.tokens;
 
         let expected = [
@@ -269,6 +1104,8 @@ mod tests {
             TokenType::RightParen,
             TokenType::LeftBrace,
             TokenType::RightBrace,
+            TokenType::LeftBracket,
+            TokenType::RightBracket,
             TokenType::Dot,
             TokenType::Comma,
             TokenType::Minus,
@@ -279,10 +1116,9 @@ mod tests {
             TokenType::Eof,
         ];
 
-        assert_eq!(tokens.len(), expected.len());
-        for (token, token_type) in tokens.iter().zip(expected.iter()) {
-            assert!(token_type_eq(&token.token_type, token_type));
-        }
+        let token_types: Vec<TokenType> =
+            tokens.into_iter().map(|token| token.token_type).collect();
+        assert_eq!(token_types, expected);
     }
 
     #[test]
@@ -298,7 +1134,80 @@ mod tests {
         assert_token(&tokens[5], TokenType::LessEqual, "<=", None, 1);
         assert_token(&tokens[6], TokenType::Greater, ">", None, 1);
         assert_token(&tokens[7], TokenType::GreaterEqual, ">=", None, 1);
-        assert!(token_type_eq(&tokens[8].token_type, &TokenType::Eof));
+        assert_eq!(tokens[8].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn scan_star_star_apart_from_star() {
+        let scanner = scan("* ** *= ***");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Star, "*", None, 1);
+        assert_token(&tokens[1], TokenType::StarStar, "**", None, 1);
+        assert_token(&tokens[2], TokenType::StarEqual, "*=", None, 1);
+        assert_token(&tokens[3], TokenType::StarStar, "**", None, 1);
+        assert_token(&tokens[4], TokenType::Star, "*", None, 1);
+    }
+
+    #[test]
+    fn reset_scanner_scans_new_source() {
+        let mut scanner = Scanner::new("var a = @;\n(");
+        scanner.scan_tokens();
+        assert!(scanner.had_error());
+
+        scanner.reset("print \"b\";");
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], TokenType::Print, "print", None, 1);
+        assert_token(
+            &tokens[1],
+            TokenType::String,
+            "b",
+            Some(Literal::Str("b".to_string())),
+            1,
+        );
+        assert_token(&tokens[2], TokenType::Semicolon, ";", None, 1);
+        assert_eq!(tokens[3].span, 10..10);
+        assert!(scanner.diagnostics().is_empty());
+        assert_eq!(scanner.max_depth(), 0);
+        // Only the new source's lexemes stay interned.
+        assert!(!scanner.lexemes.contains_key("var"));
+    }
+
+    #[test]
+    fn stats_count_tokens_and_lines() {
+        let source = "var name = \"Lox\";\n// comment\nprint name + 1.5 * 2;\n";
+        let scanner = scan(source);
+
+        assert_eq!(
+            scanner.stats(),
+            ScanStats {
+                token_count: 13,
+                line_count: 3,
+                string_count: 1,
+                number_count: 2,
+                identifier_count: 2,
+            }
+        );
+        assert_eq!(scan("").stats().line_count, 0);
+        assert_eq!(scan("a\nb").stats().line_count, 2);
+    }
+
+    #[test]
+    fn scan_bitwise_operators() {
+        let scanner = scan("& | ^ << >> <<= and or");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Ampersand, "&", None, 1);
+        assert_token(&tokens[1], TokenType::Pipe, "|", None, 1);
+        assert_token(&tokens[2], TokenType::Caret, "^", None, 1);
+        assert_token(&tokens[3], TokenType::LessLess, "<<", None, 1);
+        assert_token(&tokens[4], TokenType::GreaterGreater, ">>", None, 1);
+        assert_token(&tokens[5], TokenType::LessLess, "<<", None, 1);
+        assert_token(&tokens[6], TokenType::Equal, "=", None, 1);
+        assert_token(&tokens[7], TokenType::And, "and", None, 1);
+        assert_token(&tokens[8], TokenType::Or, "or", None, 1);
     }
 
     #[test]
@@ -306,10 +1215,28 @@ mod tests {
         let scanner = scan("123 45.67 \"hi\"");
         let tokens = scanner.tokens;
 
-        assert_token(&tokens[0], TokenType::Number, "123", Some("123"), 1);
-        assert_token(&tokens[1], TokenType::Number, "45.67", Some("45.67"), 1);
-        assert_token(&tokens[2], TokenType::String, "hi", Some("hi"), 1);
-        assert!(token_type_eq(&tokens[3].token_type, &TokenType::Eof));
+        assert_token(
+            &tokens[0],
+            TokenType::Number,
+            "123",
+            Some(Literal::Int(123)),
+            1,
+        );
+        assert_token(
+            &tokens[1],
+            TokenType::Number,
+            "45.67",
+            Some(Literal::Number(45.67)),
+            1,
+        );
+        assert_token(
+            &tokens[2],
+            TokenType::String,
+            "hi",
+            Some(Literal::Str("hi".to_string())),
+            1,
+        );
+        assert_eq!(tokens[3].token_type, TokenType::Eof);
     }
 
     #[test]
@@ -323,7 +1250,20 @@ mod tests {
         assert_token(&tokens[3], TokenType::Identifier, "bar_1", None, 1);
         assert_token(&tokens[4], TokenType::Var, "var", None, 1);
         assert_token(&tokens[5], TokenType::While, "while", None, 1);
-        assert!(token_type_eq(&tokens[6].token_type, &TokenType::Eof));
+        assert_eq!(tokens[6].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn scan_underscore_led_identifiers() {
+        let scanner = scan("_ _private __init__ _and");
+        let tokens = scanner.tokens;
+
+        assert_eq!(tokens.len(), 5);
+        assert_token(&tokens[0], TokenType::Identifier, "_", None, 1);
+        assert_token(&tokens[1], TokenType::Identifier, "_private", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "__init__", None, 1);
+        assert_token(&tokens[3], TokenType::Identifier, "_and", None, 1);
+        assert!(scanner.diagnostics.is_empty());
     }
 
     #[test]
@@ -334,12 +1274,12 @@ mod tests {
         assert_token(&tokens[0], TokenType::Var, "var", None, 1);
         assert_token(&tokens[1], TokenType::Identifier, "a", None, 1);
         assert_token(&tokens[2], TokenType::Equal, "=", None, 1);
-        assert_token(&tokens[3], TokenType::Number, "1", Some("1"), 1);
+        assert_token(&tokens[3], TokenType::Number, "1", Some(Literal::Int(1)), 1);
         assert_token(&tokens[4], TokenType::Semicolon, ";", None, 1);
         assert_token(&tokens[5], TokenType::Print, "print", None, 2);
         assert_token(&tokens[6], TokenType::Identifier, "a", None, 2);
         assert_token(&tokens[7], TokenType::Semicolon, ";", None, 2);
-        assert!(token_type_eq(&tokens[8].token_type, &TokenType::Eof));
+        assert_eq!(tokens[8].token_type, TokenType::Eof);
     }
 
     #[test]
@@ -347,12 +1287,931 @@ mod tests {
         let scanner = scan("\"unterminated");
 
         assert_eq!(scanner.tokens.len(), 1);
-        assert!(token_type_eq(
-            &scanner.tokens[0].token_type,
-            &TokenType::Eof
-        ));
-        assert_eq!(scanner.errors.len(), 1);
-        assert_eq!(scanner.errors[0].0, 1);
-        assert_eq!(scanner.errors[0].1, "Unterminated string.");
+        assert_eq!(scanner.tokens[0].token_type, TokenType::Eof);
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].line, 1);
+        assert_eq!(scanner.diagnostics[0].message, "Unterminated string.");
+    }
+
+    #[test]
+    fn scan_single_line_block_comment() {
+        let scanner = scan("1 /* comment */ 2");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Number, "1", Some(Literal::Int(1)), 1);
+        assert_token(&tokens[1], TokenType::Number, "2", Some(Literal::Int(2)), 1);
+        assert_eq!(tokens[2].token_type, TokenType::Eof);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_multi_line_block_comment() {
+        let scanner = scan("1 /* first\nsecond\nthird */ 2");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Number, "1", Some(Literal::Int(1)), 1);
+        assert_token(&tokens[1], TokenType::Number, "2", Some(Literal::Int(2)), 3);
+        assert_eq!(tokens[2].token_type, TokenType::Eof);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_unterminated_block_comment_records_error() {
+        let scanner = scan("1\n/* never\nclosed");
+
+        assert_eq!(scanner.tokens.len(), 2);
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].line, 2);
+        assert_eq!(
+            scanner.diagnostics[0].message,
+            "Unterminated block comment."
+        );
+    }
+
+    #[test]
+    fn scan_nested_block_comment() {
+        let scanner = scan("1 /* outer /* inner */ still outer */ 2");
+        let tokens = scanner.tokens;
+
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], TokenType::Number, "1", Some(Literal::Int(1)), 1);
+        assert_token(&tokens[1], TokenType::Number, "2", Some(Literal::Int(2)), 1);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_three_levels_of_nested_block_comments() {
+        let scanner = scan("1 /* one\n/* two\n/* three */\n*/\n*/ 2");
+        let tokens = scanner.tokens;
+
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], TokenType::Number, "1", Some(Literal::Int(1)), 1);
+        assert_token(&tokens[1], TokenType::Number, "2", Some(Literal::Int(2)), 5);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_unbalanced_nested_block_comment_records_error() {
+        let scanner = scan("1\n/* outer\n/* inner */\nno close");
+
+        assert_eq!(scanner.tokens.len(), 2);
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].line, 2);
+        assert_eq!(
+            scanner.diagnostics[0].message,
+            "Unterminated block comment."
+        );
+    }
+
+    #[test]
+    fn scan_string_escape_sequences() {
+        let scanner = scan("\"line1\\nline2\" \"\\t\\r\\\\\\\"\\0\"");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::String,
+            "line1\\nline2",
+            Some(Literal::Str("line1\nline2".to_string())),
+            1,
+        );
+        assert_eq!(
+            tokens[1].literal,
+            Some(Literal::Str("\t\r\\\"\0".to_string()))
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_unknown_escape_sequence_records_error() {
+        let scanner = scan("\"\\z\"");
+
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].line, 1);
+        assert_eq!(
+            scanner.diagnostics[0].message,
+            "Unknown escape sequence '\\z'."
+        );
+    }
+
+    #[test]
+    fn scan_non_ascii_string_and_comment() {
+        let scanner = scan("// ¿qué? 🦀\n\"héllo 🌮\" 1");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::String,
+            "héllo 🌮",
+            Some(Literal::Str("héllo 🌮".to_string())),
+            2,
+        );
+        assert_token(&tokens[1], TokenType::Number, "1", Some(Literal::Int(1)), 2);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_unicode_identifiers() {
+        let scanner = scan("café λ class");
+        let tokens = scanner.tokens;
+
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], TokenType::Identifier, "café", None, 1);
+        assert_token(&tokens[1], TokenType::Identifier, "λ", None, 1);
+        assert_token(&tokens[2], TokenType::Class, "class", None, 1);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_radix_number_literals() {
+        let scanner = scan("0xFF 0b1010 0o17 0X1f");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::Number,
+            "0xFF",
+            Some(Literal::Int(255)),
+            1,
+        );
+        assert_token(
+            &tokens[1],
+            TokenType::Number,
+            "0b1010",
+            Some(Literal::Int(10)),
+            1,
+        );
+        assert_token(
+            &tokens[2],
+            TokenType::Number,
+            "0o17",
+            Some(Literal::Int(15)),
+            1,
+        );
+        assert_token(
+            &tokens[3],
+            TokenType::Number,
+            "0X1f",
+            Some(Literal::Int(31)),
+            1,
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_malformed_radix_number_records_error() {
+        let scanner = scan("0xG1 0b012");
+
+        assert_eq!(scanner.tokens.len(), 1);
+        assert_eq!(scanner.diagnostics.len(), 2);
+        assert_eq!(
+            scanner.diagnostics[0].message,
+            "Invalid digit 'G' in hexadecimal literal."
+        );
+        assert_eq!(
+            scanner.diagnostics[1].message,
+            "Invalid digit '2' in binary literal."
+        );
+    }
+
+    #[test]
+    fn scan_scientific_notation() {
+        let scanner = scan("1e3 2.5e-4 6.022E23");
+        let tokens = scanner.tokens;
+
+        assert_eq!(tokens.len(), 4);
+        assert_token(
+            &tokens[0],
+            TokenType::Number,
+            "1e3",
+            Some(Literal::Number(1000.0)),
+            1,
+        );
+        assert_token(
+            &tokens[1],
+            TokenType::Number,
+            "2.5e-4",
+            Some(Literal::Number(0.00025)),
+            1,
+        );
+        assert_token(
+            &tokens[2],
+            TokenType::Number,
+            "6.022E23",
+            Some(Literal::Number(6.022e23)),
+            1,
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_dangling_exponent_records_error() {
+        let scanner = scan("1e+");
+
+        assert_eq!(scanner.tokens.len(), 1);
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].message, "Expect digits in exponent.");
+    }
+
+    #[test]
+    fn scan_digit_separators() {
+        let scanner = scan("1_000 1.234_5 1_0e1_0");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::Number,
+            "1_000",
+            Some(Literal::Int(1000)),
+            1,
+        );
+        assert_token(
+            &tokens[1],
+            TokenType::Number,
+            "1.234_5",
+            Some(Literal::Number(1.2345)),
+            1,
+        );
+        assert_token(
+            &tokens[2],
+            TokenType::Number,
+            "1_0e1_0",
+            Some(Literal::Number(10e10)),
+            1,
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_misplaced_digit_separators_record_errors() {
+        for source in ["1__0", "1_", "1._5", "1_.5"] {
+            let scanner = scan(source);
+
+            assert_eq!(scanner.diagnostics.len(), 1, "{}", source);
+            assert_eq!(
+                scanner.diagnostics[0].message,
+                "Digit separator '_' must appear between two digits."
+            );
+        }
+    }
+
+    #[test]
+    fn scan_tracks_columns() {
+        let scanner = scan("var x = 1;\n  print x;");
+        let columns: Vec<(usize, usize)> = scanner
+            .tokens
+            .iter()
+            .map(|token| (token.line, token.column))
+            .collect();
+
+        assert_eq!(
+            columns,
+            [
+                (1, 1),
+                (1, 5),
+                (1, 7),
+                (1, 9),
+                (1, 10),
+                (2, 3),
+                (2, 9),
+                (2, 10),
+                (2, 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_error_records_column() {
+        let scanner = scan("var x = @;");
+
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].line, 1);
+        assert_eq!(scanner.diagnostics[0].column, 9);
+    }
+
+    #[test]
+    fn scan_spans_reconstruct_source_text() {
+        let source = "var s = \"hi\\n\"; // note\nprint s + 1.5;";
+        let scanner = scan(source);
+        let texts: Vec<&str> = scanner
+            .tokens
+            .iter()
+            .map(|token| &source[token.span.clone()])
+            .collect();
+
+        assert_eq!(
+            texts,
+            [
+                "var",
+                "s",
+                "=",
+                "\"hi\\n\"",
+                ";",
+                "print",
+                "s",
+                "+",
+                "1.5",
+                ";",
+                ""
+            ]
+        );
+        assert_eq!(
+            scanner.tokens.last().unwrap().span,
+            source.len()..source.len()
+        );
+    }
+
+    #[test]
+    fn scan_trailing_decimal_point_warns() {
+        let scanner = scan("123.;");
+
+        assert_eq!(
+            scanner.diagnostics,
+            [Diagnostic::warning(
+                1,
+                4,
+                "A number literal cannot end with '.'.".to_string()
+            )]
+        );
+        assert!(!scanner.had_error());
+        assert_token(
+            &scanner.tokens[0],
+            TokenType::Number,
+            "123.",
+            Some(Literal::Number(123.0)),
+            1,
+        );
+        assert_eq!(scanner.tokens[1].token_type, TokenType::Semicolon);
+
+        let scanner = scan("123.5");
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_tracks_max_nesting_depth() {
+        let scanner = scan("((([{}]))) (())");
+
+        assert_eq!(scanner.max_depth(), 5);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_beyond_depth_limit_records_error() {
+        let source = format!("{}1{}", "(".repeat(10), ")".repeat(10));
+        let mut scanner = Scanner::new(&source).with_depth_limit(4);
+        scanner.scan_tokens();
+
+        assert_eq!(scanner.max_depth(), 10);
+        assert_eq!(
+            scanner.diagnostics(),
+            [Diagnostic::error(
+                1,
+                5,
+                "Nesting is too deep (more than 4 levels).".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn scan_mismatched_closers_warn() {
+        let scanner = scan(
+            "(]
+}",
+        );
+
+        let messages: Vec<&str> = scanner
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Mismatched ']'; expected a closer for '('.",
+                "Unmatched '}'."
+            ]
+        );
+        assert!(!scanner.had_error());
+        assert_eq!(scanner.diagnostics[1].line, 2);
+    }
+
+    #[test]
+    fn scan_rejects_source_over_size_limit() {
+        let mut scanner = Scanner::new("print 1;").with_max_source_bytes(4);
+        scanner.scan_tokens();
+
+        assert_eq!(
+            scanner.diagnostics(),
+            [Diagnostic::error(
+                1,
+                1,
+                "Source is larger than the 4 byte limit.".to_string()
+            )]
+        );
+        assert_eq!(scanner.tokens().len(), 1);
+
+        let mut scanner = Scanner::new("print 1;").with_max_source_bytes(8);
+        scanner.scan_tokens();
+        assert!(!scanner.had_error());
+    }
+
+    #[test]
+    fn scan_tokens_checked_returns_errors() {
+        let mut scanner = Scanner::new("\"unterminated");
+        let result = scanner.scan_tokens_checked();
+
+        let errors = result.expect_err("scanning should fail");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated string.");
+    }
+
+    #[test]
+    fn scan_tokens_checked_returns_tokens() {
+        let mut scanner = Scanner::new("1 + 2");
+        let result = scanner.scan_tokens_checked();
+
+        assert_eq!(result.map(Vec::len), Ok(4));
+    }
+
+    #[test]
+    fn diagnostics_accessor_exposes_errors() {
+        let mut scanner = Scanner::new("var a = @;\n\"open");
+        scanner.scan_tokens();
+
+        assert!(scanner.had_error());
+        let reported: Vec<(usize, &str)> = scanner
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            [(1, "Unexpected character: @"), (2, "Unterminated string.")]
+        );
+    }
+
+    #[test]
+    fn unterminated_names_what_the_source_ended_inside() {
+        let mut scanner = Scanner::new("print r\"open");
+        scanner.scan_tokens();
+        assert_eq!(scanner.unterminated(), Some(Unterminated::String));
+
+        scanner.reset("/* open");
+        scanner.scan_tokens();
+        assert_eq!(scanner.unterminated(), Some(Unterminated::BlockComment));
+
+        scanner.reset("print \"closed\"; @");
+        scanner.scan_tokens();
+        assert_eq!(scanner.unterminated(), None);
+    }
+
+    #[test]
+    fn streaming_matches_scan_tokens() {
+        let source = "fun add(a, b) {\n  return a + b; // sum\n}\nprint add(1, 2.5) == \"3.5\";";
+        let scanned = scan(source).tokens;
+        let streamed: Vec<Token> = Scanner::new(source).into_iter().collect();
+
+        assert_eq!(streamed, scanned);
+    }
+
+    #[test]
+    fn streaming_ends_with_single_eof() {
+        let mut tokens = Scanner::new("/* only a comment */").into_iter();
+
+        assert_eq!(tokens.next().unwrap().token_type, TokenType::Eof);
+        assert!(tokens.next().is_none());
+        assert!(tokens.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn scan_const_keyword() {
+        let scanner = scan("const constant");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Const, "const", None, 1);
+        assert_token(&tokens[1], TokenType::Identifier, "constant", None, 1);
+    }
+
+    #[test]
+    fn scan_switch_keywords() {
+        let scanner = scan("switch case default defaults");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Switch, "switch", None, 1);
+        assert_token(&tokens[1], TokenType::Case, "case", None, 1);
+        assert_token(&tokens[2], TokenType::Default, "default", None, 1);
+        assert_token(&tokens[3], TokenType::Identifier, "defaults", None, 1);
+    }
+
+    #[test]
+    fn scan_foreach_keywords() {
+        let scanner = scan("foreach (x in xs) inside");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Foreach, "foreach", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "x", None, 1);
+        assert_token(&tokens[3], TokenType::In, "in", None, 1);
+        assert_token(&tokens[6], TokenType::Identifier, "inside", None, 1);
+    }
+
+    #[test]
+    fn print_is_a_keyword_by_default() {
+        let scanner = scan("print(x);");
+
+        assert_token(&scanner.tokens[0], TokenType::Print, "print", None, 1);
+    }
+
+    #[test]
+    fn print_can_scan_as_identifier() {
+        let mut scanner = Scanner::new("print(x); write x;").with_print_as_keyword(false);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens();
+
+        assert_token(&tokens[0], TokenType::Identifier, "print", None, 1);
+        assert_token(&tokens[5], TokenType::Write, "write", None, 1);
+    }
+
+    #[test]
+    fn scan_loop_control_keywords() {
+        let scanner = scan("break continue breakpoint continued");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Break, "break", None, 1);
+        assert_token(&tokens[1], TokenType::Continue, "continue", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "breakpoint", None, 1);
+        assert_token(&tokens[3], TokenType::Identifier, "continued", None, 1);
+    }
+
+    #[test]
+    fn scan_recovers_after_illegal_characters() {
+        let source = "a $ b\n€ c\n\"d\"";
+        let scanner = scan(source);
+        let tokens = &scanner.tokens;
+
+        assert_eq!(scanner.diagnostics.len(), 2);
+        assert_eq!(scanner.diagnostics[0].message, "Unexpected character: $");
+        assert_eq!(
+            (scanner.diagnostics[1].line, scanner.diagnostics[1].column),
+            (2, 1)
+        );
+        assert_token(&tokens[0], TokenType::Identifier, "a", None, 1);
+        assert_token(&tokens[1], TokenType::Identifier, "b", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "c", None, 2);
+        assert_eq!(tokens[2].column, 3);
+        assert_eq!(&source[tokens[2].span.clone()], "c");
+        assert_token(
+            &tokens[3],
+            TokenType::String,
+            "d",
+            Some(Literal::Str("d".to_string())),
+            3,
+        );
+        assert_eq!(tokens[4].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn scan_coalesces_adjacent_unexpected_characters() {
+        let scanner = scan("@#$ 1 @\n$");
+
+        assert_eq!(scanner.diagnostics.len(), 3);
+        assert_eq!(
+            scanner.diagnostics[0],
+            Diagnostic::error(1, 1, "Unexpected characters: @, #, $".to_string())
+        );
+        assert_eq!(
+            scanner.diagnostics[1],
+            Diagnostic::error(1, 7, "Unexpected character: @".to_string())
+        );
+        assert_eq!(
+            scanner.diagnostics[2],
+            Diagnostic::error(2, 1, "Unexpected character: $".to_string())
+        );
+        assert_token(
+            &scanner.tokens[0],
+            TokenType::Number,
+            "1",
+            Some(Literal::Int(1)),
+            1,
+        );
+    }
+
+    #[test]
+    fn scan_unterminated_string_reports_opening_line() {
+        let scanner = scan("1\n  \"opened here\nstill\ngoing\nend");
+
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(
+            scanner.diagnostics[0],
+            Diagnostic::error(2, 3, "Unterminated string.".to_string())
+        );
+        assert_eq!(scanner.line, 5);
+    }
+
+    #[test]
+    fn scan_percent_operator() {
+        let scanner = scan("a % b / c");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[0], TokenType::Identifier, "a", None, 1);
+        assert_token(&tokens[1], TokenType::Percent, "%", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "b", None, 1);
+        assert_token(&tokens[3], TokenType::Slash, "/", None, 1);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_char_literals() {
+        let scanner = scan("'a' '\\n' 'é'");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::Char,
+            "a",
+            Some(Literal::Char('a')),
+            1,
+        );
+        assert_token(
+            &tokens[1],
+            TokenType::Char,
+            "\\n",
+            Some(Literal::Char('\n')),
+            1,
+        );
+        assert_token(
+            &tokens[2],
+            TokenType::Char,
+            "é",
+            Some(Literal::Char('é')),
+            1,
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_invalid_char_literals_record_errors() {
+        let scanner = scan("'' 'ab' 1");
+
+        assert_eq!(scanner.diagnostics.len(), 2);
+        assert_eq!(scanner.diagnostics[0].message, "Empty character literal.");
+        assert_eq!(
+            scanner.diagnostics[1].message,
+            "Character literal must contain exactly one character."
+        );
+        assert_token(
+            &scanner.tokens[0],
+            TokenType::Number,
+            "1",
+            Some(Literal::Int(1)),
+            1,
+        );
+    }
+
+    #[test]
+    fn scan_raw_strings() {
+        let scanner = scan("r\"a\\nb\" route r \"x\"");
+        let tokens = scanner.tokens;
+
+        assert_token(
+            &tokens[0],
+            TokenType::String,
+            "a\\nb",
+            Some(Literal::Str("a\\nb".to_string())),
+            1,
+        );
+        assert_token(&tokens[1], TokenType::Identifier, "route", None, 1);
+        assert_token(&tokens[2], TokenType::Identifier, "r", None, 1);
+        assert_token(
+            &tokens[3],
+            TokenType::String,
+            "x",
+            Some(Literal::Str("x".to_string())),
+            1,
+        );
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_unterminated_raw_string_records_error() {
+        let scanner = scan("r\"C:\\path\\");
+
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(
+            scanner.diagnostics[0],
+            Diagnostic::error(1, 1, "Unterminated raw string.".to_string())
+        );
+    }
+
+    #[test]
+    fn scan_block_string_dedents_to_closing_delimiter() {
+        let source = "var s = \"\"\"\n    first\n      \"indented\"\n\n    last\\n\n    \"\"\";";
+        let scanner = scan(source);
+        let tokens = scanner.tokens;
+
+        assert!(scanner.diagnostics.is_empty());
+        assert_eq!(
+            tokens[3].literal,
+            Some(Literal::Str("first\n  \"indented\"\n\nlast\\n".to_string()))
+        );
+        assert_eq!(tokens[3].line, 6);
+        assert_eq!(tokens[4].token_type, TokenType::Semicolon);
+    }
+
+    #[test]
+    fn scan_block_string_on_one_line_is_kept_as_written() {
+        let scanner = scan("\"\"\"a \"b\" c\"\"\" \"\"");
+        let tokens = scanner.tokens;
+
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::Str("a \"b\" c".to_string()))
+        );
+        assert_eq!(tokens[1].literal, Some(Literal::Str(String::new())));
+    }
+
+    #[test]
+    fn scan_unterminated_block_string_reports_start_line() {
+        let scanner = scan("print 1;\nprint \"\"\"\nopen\n\"\"");
+
+        assert_eq!(
+            scanner.diagnostics,
+            [Diagnostic::error(
+                2,
+                7,
+                "Unterminated block string.".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn peek_at_looks_ahead_by_characters() {
+        let mut scanner = Scanner::new("aé\n");
+
+        assert_eq!(scanner.peek_at(0), 'a');
+        assert_eq!(scanner.peek_at(1), 'é');
+        assert_eq!(scanner.peek_at(2), '\n');
+        assert_eq!(scanner.peek_at(3), '\0');
+        assert_eq!(scanner.peek_at(10), '\0');
+
+        scanner.advance();
+        assert_eq!(scanner.peek(), 'é');
+        assert_eq!(scanner.peek_next(), '\n');
+        assert_eq!(scanner.peek_at(2), '\0');
+    }
+
+    #[test]
+    fn lossless_scan_reproduces_source() {
+        let source = "// greet\nvar s = \"hi\\n\"; /* a\n /* nested */ b */\r\n\tprint s + 'c';\n";
+        let mut scanner = Scanner::new_lossless(source);
+        let tokens = scanner.scan_tokens();
+
+        let text: String = tokens.iter().map(|token| &*token.lexeme).collect();
+        assert_eq!(text, source);
+        assert!(matches!(tokens[0].token_type, TokenType::Comment));
+        assert_eq!(&*tokens[1].lexeme, "\n");
+        assert!(matches!(tokens[1].token_type, TokenType::Whitespace));
+        assert_eq!(&*tokens[8].lexeme, "\"hi\\n\"");
+        assert_eq!(tokens[8].literal, Some(Literal::Str("hi\n".to_string())));
+        assert_eq!(&*tokens[11].lexeme, "/* a\n /* nested */ b */");
+        assert_eq!(tokens[11].line, 3);
+        assert_eq!(&*tokens[12].lexeme, "\r\n\t");
+    }
+
+    #[test]
+    fn default_scan_drops_trivia() {
+        let scanner = scan("// note\n  1 /* two */\n");
+
+        let types: Vec<String> = scanner
+            .tokens
+            .iter()
+            .map(|token| token.token_type.to_string())
+            .collect();
+        assert_eq!(types, ["NUMBER", "EOF"]);
+        assert_eq!(scanner.tokens[0].line, 2);
+    }
+
+    #[test]
+    fn tokens_to_json_lists_every_token() {
+        let scanner = scan("1 + 2");
+
+        assert_eq!(
+            scanner.tokens_to_json(),
+            concat!(
+                r#"[{"type":"NUMBER","lexeme":"1","literal":1,"line":1},"#,
+                r#"{"type":"PLUS","lexeme":"+","literal":null,"line":1},"#,
+                r#"{"type":"NUMBER","lexeme":"2","literal":2,"line":1},"#,
+                r#"{"type":"EOF","lexeme":"","literal":null,"line":1}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn keywords_resolve_from_table() {
+        for (keyword, token_type) in keywords() {
+            let scanner = scan(keyword);
+            assert_eq!(&scanner.tokens[0].token_type, token_type);
+            assert_eq!(&*scanner.tokens[0].lexeme, *keyword);
+        }
+        assert_eq!(keywords().len(), 25);
+
+        for text in ["foo", "classy", "If", "_while"] {
+            let scanner = scan(text);
+            assert_eq!(scanner.tokens[0].token_type, TokenType::Identifier);
+        }
+    }
+
+    #[test]
+    fn equal_lexemes_share_one_allocation() {
+        let scanner = scan("var count = count + 1;");
+        let tokens = &scanner.tokens;
+
+        assert_eq!(&*tokens[1].lexeme, "count");
+        assert_eq!(&*tokens[3].lexeme, "count");
+        assert!(Rc::ptr_eq(&tokens[1].lexeme, &tokens[3].lexeme));
+        assert!(!Rc::ptr_eq(&tokens[1].lexeme, &tokens[0].lexeme));
+    }
+
+    #[test]
+    fn tab_width_widens_columns() {
+        let mut scanner = Scanner::new("\tx\n\t\ty").with_tab_width(4);
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens[0].column, 5);
+        assert_eq!(tokens[1].column, 9);
+
+        let scanner = scan("\tx");
+        assert_eq!(scanner.tokens[0].column, 2);
+    }
+
+    #[test]
+    fn diagnostics_name_the_file() {
+        let mut scanner = Scanner::new("var a;\n\n\"open").with_file_name("foo.lox");
+        scanner.scan_tokens();
+
+        assert_eq!(scanner.file_name(), Some("foo.lox"));
+        assert_eq!(
+            scanner.diagnostics()[0].render(scanner.file_name()),
+            "foo.lox:3: Error: Unterminated string."
+        );
+
+        let scanner = scan("@");
+        assert_eq!(
+            scanner.diagnostics()[0].render(scanner.file_name()),
+            "[line 1] Error: Unexpected character: @"
+        );
+    }
+
+    #[test]
+    fn leading_byte_order_mark_is_skipped() {
+        let source = "var a = 1;\nprint a;";
+        let with_bom = format!("\u{feff}{}", source);
+        assert_eq!(&with_bom.as_bytes()[..3], [0xEF, 0xBB, 0xBF]);
+
+        let plain = scan(source);
+        let marked = scan(&with_bom);
+
+        assert!(marked.diagnostics.is_empty());
+        assert_eq!(marked.tokens.len(), plain.tokens.len());
+        for (left, right) in marked.tokens.iter().zip(plain.tokens.iter()) {
+            // Spans index the source as given, BOM and all.
+            assert_eq!(&with_bom[left.span.clone()], &*left.lexeme);
+            let unshifted = Token {
+                span: left.span.start - 3..left.span.end - 3,
+                ..left.clone()
+            };
+            assert_eq!(&unshifted, right);
+        }
+
+        let mut lossless = Scanner::new_lossless(&with_bom);
+        let text: String = lossless
+            .scan_tokens()
+            .iter()
+            .map(|token| &*token.lexeme)
+            .collect();
+        assert_eq!(text, with_bom);
+    }
+
+    #[test]
+    fn scan_ternary_operators() {
+        let scanner = scan("a ? b : c");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[1], TokenType::Question, "?", None, 1);
+        assert_token(&tokens[3], TokenType::Colon, ":", None, 1);
+        assert!(scanner.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn scan_compound_assignment_operators() {
+        let scanner = scan("a += 1 -= 2 *= 3 /= 4 %= 5 / 6");
+        let tokens = scanner.tokens;
+
+        assert_token(&tokens[1], TokenType::PlusEqual, "+=", None, 1);
+        assert_token(&tokens[3], TokenType::MinusEqual, "-=", None, 1);
+        assert_token(&tokens[5], TokenType::StarEqual, "*=", None, 1);
+        assert_token(&tokens[7], TokenType::SlashEqual, "/=", None, 1);
+        assert_token(&tokens[9], TokenType::PercentEqual, "%=", None, 1);
+        assert_token(&tokens[11], TokenType::Slash, "/", None, 1);
+        assert!(scanner.diagnostics.is_empty());
     }
 }
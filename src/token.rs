@@ -1,37 +1,203 @@
+use crate::literal::Literal;
 use crate::token_type::TokenType;
 
 use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use std::rc::Rc;
 
-#[derive(Debug)]
+/// Tokens compare equal when every field does, including the literal, whose
+/// numbers are compared as parsed.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
-    pub literal: Option<String>,
+    /// The token's text. For strings this excludes the surrounding quotes
+    /// (unless scanned losslessly), so prefer `span` when the exact source
+    /// text is needed.
+    ///
+    /// The scanner shares one allocation between equal lexemes. Being
+    /// reference counted rather than borrowed, a token does not keep the
+    /// source alive, so it can outlive it (as functions defined at the REPL
+    /// do) and cloning it stays cheap.
+    pub lexeme: Rc<str>,
+    pub literal: Option<Literal>,
     pub line: usize,
+    pub column: usize,
+    /// The byte range of the token in the source. This is authoritative:
+    /// slicing the source with it always yields exactly what was scanned.
+    pub span: Range<usize>,
 }
 
 impl Token {
     pub fn new(
         token_type: TokenType,
-        lexeme: String,
-        literal: Option<String>,
+        lexeme: Rc<str>,
+        literal: Option<Literal>,
         line: usize,
+        column: usize,
+        span: Range<usize>,
     ) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            span,
         }
     }
 }
 
+impl Token {
+    /// Where an error at this token is, for diagnostics: ` at 'lexeme'`, or
+    /// ` at end` for the end of input.
+    pub fn location(&self) -> String {
+        match self.token_type {
+            TokenType::Eof => " at end".to_string(),
+            _ => format!(" at '{}'", self.lexeme),
+        }
+    }
+
+    /// Serializes the token as a JSON object with `type`, `lexeme`,
+    /// `literal`, and `line` fields. A missing literal is `null`.
+    pub fn to_json(&self) -> String {
+        let literal = match &self.literal {
+            Some(Literal::Int(value)) => value.to_string(),
+            Some(Literal::Number(value)) if value.is_finite() => format!("{:?}", value),
+            Some(Literal::Str(value)) => json_string(value),
+            Some(Literal::Char(value)) => json_string(&value.to_string()),
+            Some(Literal::Bool(value)) => value.to_string(),
+            Some(Literal::Number(_)) | Some(Literal::Nil) | None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"type\":{},\"lexeme\":{},\"literal\":{},\"line\":{}}}",
+            json_string(&self.token_type.to_string()),
+            json_string(&self.lexeme),
+            literal,
+            self.line
+        )
+    }
+}
+
+/// Quotes `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{:?} {} {:?}",
-            self.token_type, self.lexeme, self.literal
-        )
+        write!(f, "{} {} ", self.token_type, self.lexeme)?;
+        match &self.literal {
+            Some(literal) => write!(f, "{}", literal),
+            None => write!(f, "null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn display_number_token() {
+        let token = Token::new(
+            TokenType::Number,
+            "123".into(),
+            Some(Literal::Number(123.0)),
+            1,
+            1,
+            0..3,
+        );
+
+        assert_eq!(token.to_string(), "NUMBER 123 123.0");
+    }
+
+    #[test]
+    fn display_eof_token() {
+        let token = Token::new(TokenType::Eof, "".into(), None, 1, 1, 0..0);
+
+        assert_eq!(token.to_string(), "EOF  null");
+    }
+
+    #[test]
+    fn tokens_compare_by_value() {
+        let number = |value| {
+            Token::new(
+                TokenType::Number,
+                "1".into(),
+                Some(Literal::Number(value)),
+                1,
+                1,
+                0..1,
+            )
+        };
+
+        assert_eq!(number(1.0), number(1.0));
+        assert_ne!(number(1.0), number(2.0));
+        assert_ne!(number(f64::NAN), number(f64::NAN));
+    }
+
+    #[test]
+    fn token_types_work_as_set_members() {
+        let statement_starts: HashSet<TokenType> = [
+            TokenType::Class,
+            TokenType::Fun,
+            TokenType::Var,
+            TokenType::Fun,
+        ]
+        .into();
+
+        assert_eq!(statement_starts.len(), 3);
+        assert!(statement_starts.contains(&TokenType::Var));
+        assert!(!statement_starts.contains(&TokenType::Identifier));
+    }
+
+    #[test]
+    fn display_token_type() {
+        assert_eq!(TokenType::LeftParen.to_string(), "LEFT_PAREN");
+        assert_eq!(TokenType::Star.to_string(), "STAR");
+        assert_eq!(TokenType::Identifier.to_string(), "IDENTIFIER");
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        let token = Token::new(
+            TokenType::String,
+            "a\\\"b\n".into(),
+            Some(Literal::Str("a\"b\n\u{1}".to_string())),
+            2,
+            1,
+            0..8,
+        );
+
+        assert_eq!(
+            token.to_json(),
+            r#"{"type":"STRING","lexeme":"a\\\"b\n","literal":"a\"b\n\u0001","line":2}"#
+        );
+    }
+
+    #[test]
+    fn location_names_lexeme_or_end() {
+        let token = Token::new(TokenType::RightBrace, "}".into(), None, 3, 1, 0..1);
+        assert_eq!(token.location(), " at '}'");
+
+        let token = Token::new(TokenType::Eof, "".into(), None, 3, 2, 1..1);
+        assert_eq!(token.location(), " at end");
     }
 }
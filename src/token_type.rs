@@ -1,17 +1,27 @@
-#[derive(Debug)]
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
+    Percent,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
+    Ampersand,
+    Pipe,
+    Caret,
 
     // One or two character tokens.
     Bang,
@@ -22,29 +32,126 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+    MinusEqual,
+    PercentEqual,
+    PlusEqual,
+    SlashEqual,
+    StarEqual,
+    StarStar,
 
     // Literals.
     Identifier,
     String,
+    Char,
     Number,
 
     // Keywords.
     And,
+    Break,
+    Case,
     Class,
+    Const,
+    Continue,
+    Default,
     Else,
     False,
     Fun,
     For,
+    Foreach,
     If,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
     While,
+    Write,
+
+    // Trivia, only produced by a lossless scanner.
+    Whitespace,
+    Comment,
 
     Eof,
 }
+
+impl Display for TokenType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TokenType::LeftParen => "LEFT_PAREN",
+            TokenType::RightParen => "RIGHT_PAREN",
+            TokenType::LeftBrace => "LEFT_BRACE",
+            TokenType::RightBrace => "RIGHT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
+            TokenType::Comma => "COMMA",
+            TokenType::Dot => "DOT",
+            TokenType::Minus => "MINUS",
+            TokenType::Percent => "PERCENT",
+            TokenType::Plus => "PLUS",
+            TokenType::Semicolon => "SEMICOLON",
+            TokenType::Slash => "SLASH",
+            TokenType::Star => "STAR",
+            TokenType::Question => "QUESTION",
+            TokenType::Colon => "COLON",
+            TokenType::Ampersand => "AMPERSAND",
+            TokenType::Pipe => "PIPE",
+            TokenType::Caret => "CARET",
+            TokenType::Bang => "BANG",
+            TokenType::BangEqual => "BANG_EQUAL",
+            TokenType::Equal => "EQUAL",
+            TokenType::EqualEqual => "EQUAL_EQUAL",
+            TokenType::Greater => "GREATER",
+            TokenType::GreaterEqual => "GREATER_EQUAL",
+            TokenType::Less => "LESS",
+            TokenType::LessEqual => "LESS_EQUAL",
+            TokenType::LessLess => "LESS_LESS",
+            TokenType::GreaterGreater => "GREATER_GREATER",
+            TokenType::MinusEqual => "MINUS_EQUAL",
+            TokenType::PercentEqual => "PERCENT_EQUAL",
+            TokenType::PlusEqual => "PLUS_EQUAL",
+            TokenType::SlashEqual => "SLASH_EQUAL",
+            TokenType::StarEqual => "STAR_EQUAL",
+            TokenType::StarStar => "STAR_STAR",
+            TokenType::Identifier => "IDENTIFIER",
+            TokenType::String => "STRING",
+            TokenType::Char => "CHAR",
+            TokenType::Number => "NUMBER",
+            TokenType::And => "AND",
+            TokenType::Break => "BREAK",
+            TokenType::Case => "CASE",
+            TokenType::Class => "CLASS",
+            TokenType::Const => "CONST",
+            TokenType::Continue => "CONTINUE",
+            TokenType::Default => "DEFAULT",
+            TokenType::Else => "ELSE",
+            TokenType::False => "FALSE",
+            TokenType::Fun => "FUN",
+            TokenType::For => "FOR",
+            TokenType::Foreach => "FOREACH",
+            TokenType::If => "IF",
+            TokenType::In => "IN",
+            TokenType::Nil => "NIL",
+            TokenType::Or => "OR",
+            TokenType::Print => "PRINT",
+            TokenType::Return => "RETURN",
+            TokenType::Super => "SUPER",
+            TokenType::Switch => "SWITCH",
+            TokenType::This => "THIS",
+            TokenType::True => "TRUE",
+            TokenType::Var => "VAR",
+            TokenType::While => "WHILE",
+            TokenType::Write => "WRITE",
+            TokenType::Whitespace => "WHITESPACE",
+            TokenType::Comment => "COMMENT",
+            TokenType::Eof => "EOF",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,259 @@
+use crate::callable::Callable;
+use crate::class::{LoxClass, LoxInstance};
+use crate::literal::Literal;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::rc::Rc;
+
+/// A runtime value produced by the interpreter.
+#[derive(Clone)]
+pub enum Value {
+    /// A whole number. Arithmetic on two `Int`s stays whole where it can;
+    /// see `Interpreter` for the exact promotion rules.
+    Int(i64),
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+    Callable(Rc<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(LoxInstance),
+    /// Arrays are shared by reference, like instances.
+    Array(Rc<RefCell<Vec<Value>>>),
+    /// Maps are shared by reference too, and keyed only by strings.
+    Map(Rc<RefCell<HashMap<String, Value>>>),
+}
+
+impl Value {
+    /// Lox truthiness: only `nil` and `false` are falsey.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The name of the value's type, as returned by `typeof`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) | Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Callable(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+        }
+    }
+
+    /// The value as a float, if it is a number of either kind.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(number) => Some(*number as f64),
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// The value as an `i64`, if it is an `Int` or a float with no
+    /// fractional part that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(number) => Some(*number),
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+                Some(*number as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Formats the value the way `print`, `str`, and the REPL show it.
+    /// Strings appear without quotes. An array or map that contains itself
+    /// prints the inner occurrence as `[...]` or `{...}`.
+    pub fn to_display_string(&self) -> String {
+        self.display(&mut Vec::new())
+    }
+
+    /// `enclosing` holds the arrays and maps being printed around this
+    /// value, to spot cycles.
+    fn display(&self, enclosing: &mut Vec<*const ()>) -> String {
+        match self {
+            Value::Int(number) => number.to_string(),
+            Value::Number(number) => format_number(*number),
+            Value::Str(string) => string.clone(),
+            Value::Bool(boolean) => boolean.to_string(),
+            Value::Nil => "nil".to_string(),
+            Value::Callable(callable) => format!("<fn {}>", callable.name()),
+            Value::Class(class) => class.name().to_string(),
+            Value::Instance(instance) => format!("{} instance", instance.class_name()),
+            Value::Array(elements) => {
+                let pointer = Rc::as_ptr(elements) as *const ();
+                if enclosing.contains(&pointer) {
+                    return "[...]".to_string();
+                }
+                enclosing.push(pointer);
+                let elements: Vec<String> = elements
+                    .borrow()
+                    .iter()
+                    .map(|element| element.display(enclosing))
+                    .collect();
+                enclosing.pop();
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Map(map) => {
+                let pointer = Rc::as_ptr(map) as *const ();
+                if enclosing.contains(&pointer) {
+                    return "{...}".to_string();
+                }
+                enclosing.push(pointer);
+                // Sorted so that printing a map is deterministic.
+                let mut entries: Vec<String> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value.display(enclosing)))
+                    .collect();
+                enclosing.pop();
+                entries.sort();
+                format!("{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+/// Whole numbers print without a fraction (`1`, not `1.0`). Magnitudes of
+/// 1e21 and up, or below 1e-7, switch to exponent notation (`1e21`,
+/// `2.5e-8`) rather than spelling out every zero, as JavaScript does.
+fn format_number(number: f64) -> String {
+    let magnitude = number.abs();
+    if number.is_finite() && magnitude != 0.0 && !(1e-7..1e21).contains(&magnitude) {
+        format!("{:e}", number)
+    } else {
+        number.to_string()
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Int(value) => Value::Int(*value),
+            Literal::Number(value) => Value::Number(*value),
+            Literal::Str(value) => Value::Str(value.clone()),
+            Literal::Char(value) => Value::Str(value.to_string()),
+            Literal::Bool(value) => Value::Bool(*value),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // An `Int` never equals a `Number`; Lox's `==` compares them by
+            // value instead.
+            (Value::Int(left), Value::Int(right)) => left == right,
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            // Callables are only equal to themselves.
+            (Value::Callable(left), Value::Callable(right)) => {
+                std::ptr::addr_eq(Rc::as_ptr(left), Rc::as_ptr(right))
+            }
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => left.is(right),
+            (Value::Array(left), Value::Array(right)) => Rc::ptr_eq(left, right),
+            (Value::Map(left), Value::Map(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "Int({})", value),
+            Value::Number(value) => write!(f, "Number({:?})", value),
+            Value::Str(value) => write!(f, "Str({:?})", value),
+            Value::Bool(value) => write!(f, "Bool({:?})", value),
+            Value::Nil => write!(f, "Nil"),
+            Value::Callable(callable) => write!(f, "Callable({})", callable.name()),
+            Value::Class(class) => write!(f, "Class({})", class.name()),
+            Value::Instance(instance) => write!(f, "Instance({})", instance.class_name()),
+            Value::Array(elements) => write!(f, "Array({:?})", elements.borrow()),
+            Value::Map(map) => write!(f, "Map({:?})", map.borrow()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::NativeFunction;
+
+    #[test]
+    fn display_numbers() {
+        assert_eq!(Value::Number(1.0).to_display_string(), "1");
+        assert_eq!(Value::Number(1.5).to_display_string(), "1.5");
+        assert_eq!(Value::Number(-0.25).to_display_string(), "-0.25");
+        assert_eq!(Value::Number(0.0).to_display_string(), "0");
+        assert_eq!(Value::Number(123456789.0).to_display_string(), "123456789");
+        assert_eq!(Value::Number(1e21).to_display_string(), "1e21");
+        assert_eq!(Value::Number(-2.5e-8).to_display_string(), "-2.5e-8");
+        assert_eq!(Value::Number(f64::INFINITY).to_display_string(), "inf");
+    }
+
+    #[test]
+    fn ints_and_floats_are_distinct() {
+        assert_eq!(Value::Int(3).to_display_string(), "3");
+        assert_eq!(Value::Int(3), Value::Int(3));
+        assert_ne!(Value::Int(3), Value::Number(3.0));
+        assert_eq!(Value::Number(4.0).as_i64(), Some(4));
+        assert_eq!(Value::Number(4.5).as_i64(), None);
+    }
+
+    #[test]
+    fn display_other_values() {
+        assert_eq!(Value::Nil.to_display_string(), "nil");
+        assert_eq!(Value::Bool(true).to_display_string(), "true");
+        assert_eq!(Value::Str("a b".to_string()).to_display_string(), "a b");
+
+        let function = NativeFunction::new("clock", 0, |_, _| Ok(Value::Nil));
+        assert_eq!(
+            Value::Callable(Rc::new(function)).to_display_string(),
+            "<fn clock>"
+        );
+
+        let class = Rc::new(LoxClass::new("Point", None, HashMap::new()));
+        let instance = LoxInstance::new(Rc::clone(&class));
+        assert_eq!(Value::Class(class).to_display_string(), "Point");
+        assert_eq!(
+            Value::Instance(instance).to_display_string(),
+            "Point instance"
+        );
+
+        let array = Value::Array(Rc::new(RefCell::new(vec![
+            Value::Number(1.0),
+            Value::Str("b".to_string()),
+        ])));
+        assert_eq!(array.to_display_string(), "[1, b]");
+    }
+
+    #[test]
+    fn display_self_containing_values() {
+        let elements = Rc::new(RefCell::new(vec![Value::Int(1)]));
+        let array = Value::Array(Rc::clone(&elements));
+        elements.borrow_mut().push(array.clone());
+        assert_eq!(array.to_display_string(), "[1, [...]]");
+
+        let map = Rc::new(RefCell::new(HashMap::new()));
+        let value = Value::Map(Rc::clone(&map));
+        map.borrow_mut().insert("self".to_string(), value.clone());
+        map.borrow_mut().insert("list".to_string(), array.clone());
+        assert_eq!(value.to_display_string(), "{list: [1, [...]], self: {...}}");
+
+        // The same array twice, but not inside itself, prints in full.
+        let shared = Value::Array(Rc::new(RefCell::new(vec![array.clone(), array])));
+        assert_eq!(shared.to_display_string(), "[[1, [...]], [1, [...]]]");
+    }
+}